@@ -0,0 +1,296 @@
+use crate::metrics::{format_short, format_sleep_minutes, format_volume, Metric};
+use crate::models::Totals;
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The total a milestone is measured against. A milestone can be unlocked
+/// again in each new period, e.g. every year for a yearly one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Daily,
+    Monthly,
+    Yearly,
+}
+
+impl Period {
+    fn value(self, totals: Totals) -> i64 {
+        match self {
+            Period::Daily => totals.daily,
+            Period::Monthly => totals.monthly,
+            Period::Yearly => totals.yearly,
+        }
+    }
+
+    // The period containing `date`: `2025-03-04`, `2025-03` or `2025`
+    fn key(self, date: NaiveDate) -> String {
+        match self {
+            Period::Daily => date.to_string(),
+            Period::Monthly => format!("{}-{:02}", date.year(), date.month()),
+            Period::Yearly => date.year().to_string(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Period::Daily => "in a day",
+            Period::Monthly => "this month",
+            Period::Yearly => "this year",
+        }
+    }
+}
+
+/// A total to reach, in the metric's unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Milestone {
+    pub metric: String,
+    pub period: Period,
+    pub threshold: i64,
+    pub title: String,
+}
+
+impl Milestone {
+    pub fn new(metric: &str, period: Period, threshold: i64, title: impl Into<String>) -> Self {
+        Self {
+            metric: metric.to_string(),
+            period,
+            threshold,
+            title: title.into(),
+        }
+    }
+
+    /// Stable identifier, e.g. `steps.yearly.1000000`.
+    pub fn id(&self) -> String {
+        let period = match self.period {
+            Period::Daily => "daily",
+            Period::Monthly => "monthly",
+            Period::Yearly => "yearly",
+        };
+        format!("{}.{}.{}", self.metric, period, self.threshold)
+    }
+}
+
+/// The milestones checked unless the configuration turns them off.
+pub fn default_milestones() -> Vec<Milestone> {
+    use Period::*;
+    vec![
+        Milestone::new("steps", Daily, 10_000, "10K steps in a day"),
+        Milestone::new("steps", Daily, 20_000, "20K steps in a day"),
+        Milestone::new("steps", Daily, 30_000, "30K steps in a day"),
+        Milestone::new("steps", Monthly, 250_000, "250K steps this month"),
+        Milestone::new("steps", Monthly, 500_000, "500K steps this month"),
+        Milestone::new("steps", Yearly, 1_000_000, "1M steps this year"),
+        Milestone::new("steps", Yearly, 2_500_000, "2.5M steps this year"),
+        Milestone::new("steps", Yearly, 5_000_000, "5M steps this year"),
+        Milestone::new("water", Daily, 3_000, "3 L of water in a day"),
+        Milestone::new("water", Monthly, 75_000, "75 L of water this month"),
+        Milestone::new("water", Yearly, 1_000_000, "1,000 L of water this year"),
+        Milestone::new("sleep", Monthly, 240 * 60, "240 hours of sleep this month"),
+        Milestone::new("sleep", Yearly, 2_500 * 60, "2,500 hours of sleep this year"),
+    ]
+}
+
+/// A title for a milestone that wasn't given one, e.g. `15K steps in a day`.
+pub fn describe(metric_id: &str, period: Period, threshold: i64) -> String {
+    let amount = match metric_id {
+        "steps" => format!("{} steps", format_short(threshold)),
+        "water" => format!("{} of water", format_volume(threshold)),
+        "sleep" => format!("{} of sleep", format_sleep_minutes(threshold)),
+        _ => threshold.to_string(),
+    };
+    format!("{} {}", amount, period.describe())
+}
+
+/// An achievement that was unlocked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unlock {
+    /// A milestone id, or `{metric}.best` for a personal best day.
+    pub id: String,
+    pub metric: String,
+    /// The day, month or year it was unlocked for.
+    pub period: String,
+    pub title: String,
+    pub unlocked_at: DateTime<Local>,
+}
+
+/// The highest daily total seen for a metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersonalBest {
+    pub value: i64,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    unlocked: Vec<Unlock>,
+    bests: BTreeMap<String, PersonalBest>,
+}
+
+/// Milestones and personal bests, checked against each fetch and kept in a
+/// JSON file.
+///
+/// The first data seen for a metric only sets the baseline: milestones it
+/// has already passed are recorded without being announced, as is its best
+/// day so far. After that, each milestone unlocks once per period and a new
+/// personal best at most once a day.
+pub struct Achievements {
+    path: Option<PathBuf>,
+    milestones: Vec<Milestone>,
+    state: State,
+    duration: Duration,
+    image_key: String,
+}
+
+/// Where achievements are kept unless configured otherwise.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("step-tracker-rpc").join("achievements.json"))
+}
+
+impl Achievements {
+    /// Load the achievements saved at `path`, starting empty if there are none.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let state = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            state,
+            ..Self::in_memory()
+        })
+    }
+
+    /// Achievements that are never saved.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            milestones: default_milestones(),
+            state: State::default(),
+            duration: Duration::from_secs(15),
+            image_key: String::new(),
+        }
+    }
+
+    /// Check these milestones instead of the defaults.
+    pub fn with_milestones(mut self, milestones: Vec<Milestone>) -> Self {
+        self.milestones = milestones;
+        self
+    }
+
+    /// How long an unlocked achievement is shown.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Image shown with unlocked achievements instead of the metric's own.
+    pub fn with_image_key(mut self, image_key: impl Into<String>) -> Self {
+        self.image_key = image_key.into();
+        self
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn image_key(&self) -> &str {
+        &self.image_key
+    }
+
+    pub fn milestones(&self) -> &[Milestone] {
+        &self.milestones
+    }
+
+    /// Every unlocked achievement, oldest first.
+    pub fn unlocked(&self) -> &[Unlock] {
+        &self.state.unlocked
+    }
+
+    pub fn personal_best(&self, metric_id: &str) -> Option<PersonalBest> {
+        self.state.bests.get(metric_id).copied()
+    }
+
+    /// Check the metric's latest data and return what it newly unlocked,
+    /// saving the achievements if anything changed.
+    pub fn check(&mut self, metric: &dyn Metric) -> io::Result<Vec<Unlock>> {
+        let (Some(totals), Some(fetched_at)) = (metric.totals(), metric.fetched_at()) else {
+            return Ok(Vec::new());
+        };
+        let date = fetched_at.date_naive();
+        let id = metric.id().to_string();
+        let baseline = !self.state.bests.contains_key(&id);
+        let mut changed = false;
+        let mut unlocked = Vec::new();
+
+        for milestone in self.milestones.iter().filter(|milestone| milestone.metric == id) {
+            let unlock = Unlock {
+                id: milestone.id(),
+                metric: id.clone(),
+                period: milestone.period.key(date),
+                title: milestone.title.clone(),
+                unlocked_at: fetched_at,
+            };
+            let already = self
+                .state
+                .unlocked
+                .iter()
+                .any(|known| known.id == unlock.id && known.period == unlock.period);
+            if milestone.period.value(totals) >= milestone.threshold && !already {
+                if !baseline {
+                    unlocked.push(unlock.clone());
+                }
+                self.state.unlocked.push(unlock);
+                changed = true;
+            }
+        }
+
+        match self.state.bests.get(&id).copied() {
+            None => {
+                self.state.bests.insert(id, PersonalBest { value: totals.daily, date });
+                changed = true;
+            }
+            Some(best) if totals.daily > best.value => {
+                // A best set earlier today keeps rising silently with the day's total
+                if best.date != date && best.value > 0 {
+                    let unlock = Unlock {
+                        id: format!("{}.best", id),
+                        metric: id.clone(),
+                        period: date.to_string(),
+                        title: format!("New best day: {}", metric.format_value(totals.daily)),
+                        unlocked_at: fetched_at,
+                    };
+                    unlocked.push(unlock.clone());
+                    self.state.unlocked.push(unlock);
+                }
+                self.state.bests.insert(id, PersonalBest { value: totals.daily, date });
+                changed = true;
+            }
+            Some(_) => {}
+        }
+
+        if changed {
+            self.save()?;
+        }
+        Ok(unlocked)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec_pretty(&self.state)?;
+        // Write then rename, so a crash never leaves a truncated file behind
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, path)
+    }
+}
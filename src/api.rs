@@ -0,0 +1,329 @@
+use crate::models::*;
+use crate::retry::{CircuitBreaker, RetryPolicy};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Default timeout for a single API request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Placeholder substituted for the token in logged URLs and messages
+const REDACTED: &str = "REDACTED";
+
+/// How the API token is sent to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    /// `Authorization: Bearer <token>` header.
+    #[default]
+    #[serde(alias = "bearer")]
+    Header,
+    /// `?token=<token>` query parameter, for servers that predate header auth.
+    Query,
+}
+
+impl FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "header" | "bearer" => Ok(AuthMode::Header),
+            "query" => Ok(AuthMode::Query),
+            other => Err(format!("unknown auth mode '{}' (expected 'header' or 'query')", other)),
+        }
+    }
+}
+
+/// Errors returned by [`ApiClient`].
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request could not be sent or no response was received.
+    Request(reqwest::Error),
+    /// The server refused the credentials (HTTP 401).
+    Unauthorized { message: String },
+    /// The server answered with another non-success status code.
+    Status { status: StatusCode, message: String },
+    /// The response body could not be decoded.
+    Decode(reqwest::Error),
+    /// Too many recent failures; no request was sent.
+    CircuitOpen { retry_in: Duration },
+}
+
+impl ApiError {
+    /// Whether the failure is likely temporary and worth retrying.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Request(_) => true,
+            ApiError::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            ApiError::Unauthorized { .. } | ApiError::Decode(_) | ApiError::CircuitOpen { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Request(e) => write!(f, "request failed: {}", e),
+            ApiError::Unauthorized { message } => write!(f, "HTTP 401: {}", message),
+            ApiError::Status { status, message } => write!(f, "HTTP {}: {}", status.as_u16(), message),
+            ApiError::Decode(e) => write!(f, "invalid response body: {}", e),
+            ApiError::CircuitOpen { retry_in } => {
+                write!(f, "API unavailable, next attempt in {}s", retry_in.as_secs().max(1))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Request(e) | ApiError::Decode(e) => Some(e),
+            ApiError::Unauthorized { .. } | ApiError::Status { .. } | ApiError::CircuitOpen { .. } => None,
+        }
+    }
+}
+
+/// Blocking client for the step tracker API.
+///
+/// Holds a single connection pool, so it should be created once and reused
+/// for every request. The token is never included in errors or `Debug` output.
+///
+/// Transient failures are retried with backoff, and a circuit breaker shared
+/// by all clones stops sending requests while the server keeps failing.
+#[derive(Clone)]
+pub struct ApiClient {
+    http: Client,
+    base_url: String,
+    token: String,
+    auth: AuthMode,
+    retry: RetryPolicy,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+    timeout: Duration,
+}
+
+impl fmt::Debug for ApiClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiClient")
+            .field("base_url", &self.base_url)
+            .field("token", &REDACTED)
+            .field("auth", &self.auth)
+            .finish()
+    }
+}
+
+impl ApiClient {
+    /// Create a client. An empty token sends unauthenticated requests, which
+    /// is enough for [`ApiClient::login`] and [`ApiClient::register`].
+    pub fn new(base_url: impl Into<String>, token: impl Into<String>) -> Result<Self, ApiError> {
+        let http = Client::builder().build().map_err(ApiError::Request)?;
+
+        Ok(Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+            auth: AuthMode::default(),
+            retry: RetryPolicy::default(),
+            breaker: Arc::new(Mutex::new(CircuitBreaker::default())),
+            timeout: REQUEST_TIMEOUT,
+        })
+    }
+
+    /// How long a single attempt may take, 15 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Arc::new(Mutex::new(breaker));
+        self
+    }
+
+    pub fn with_auth_mode(mut self, auth: AuthMode) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = token.into();
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn auth_mode(&self) -> AuthMode {
+        self.auth
+    }
+
+    /// Replace every occurrence of the token in `text` with a placeholder.
+    pub fn redact(&self, text: &str) -> String {
+        if self.token.is_empty() {
+            return text.to_string();
+        }
+        text.replace(&self.token, REDACTED)
+    }
+
+    /// `GET /api/steps/summary`
+    pub fn steps_summary(&self) -> Result<StepsSummaryResponse, ApiError> {
+        self.get("/api/steps/summary", &[])
+    }
+
+    /// `GET /api/water/summary`
+    pub fn water_summary(&self) -> Result<WaterSummaryResponse, ApiError> {
+        self.get("/api/water/summary", &[])
+    }
+
+    /// `GET /api/sleep/summary` for the given `YYYY-MM-DD` date.
+    pub fn sleep_summary(&self, date: &str) -> Result<SleepResponse, ApiError> {
+        self.get("/api/sleep/summary", &[("date", date)])
+    }
+
+    /// `GET /api/{metric}/history`: daily totals from `from` to `to` inclusive,
+    /// both `YYYY-MM-DD`. Days without data are left out.
+    pub fn daily_history(&self, metric: &str, from: &str, to: &str) -> Result<DailyHistoryResponse, ApiError> {
+        self.get(&format!("/api/{}/history", metric), &[("from", from), ("to", to)])
+    }
+
+    /// `POST /api/steps`: replace the step count for a day (today if `date` is unset).
+    pub fn set_steps(&self, request: &StepsRequest) -> Result<StepsResponse, ApiError> {
+        self.post("/api/steps", request, true)
+    }
+
+    /// `POST /api/steps/add`: add to the step count for a day (today if `date` is unset).
+    ///
+    /// Not retried, as a retry after a lost response would add the steps twice.
+    pub fn add_steps(&self, request: &StepsRequest) -> Result<StepsResponse, ApiError> {
+        self.post("/api/steps/add", request, false)
+    }
+
+    /// `POST /api/water`: replace the amount drunk on a day (today if `date` is unset).
+    pub fn set_water(&self, request: &WaterRequest) -> Result<WaterResponse, ApiError> {
+        self.post("/api/water", request, true)
+    }
+
+    /// `POST /api/water/add`: add to the amount drunk on a day (today if `date` is unset).
+    ///
+    /// Not retried, like [`ApiClient::add_steps`].
+    pub fn add_water(&self, request: &WaterRequest) -> Result<WaterResponse, ApiError> {
+        self.post("/api/water/add", request, false)
+    }
+
+    /// `POST /api/sleep`: replace the minutes slept on a day (today if `date` is unset).
+    pub fn set_sleep(&self, request: &SleepRequest) -> Result<SleepEntryResponse, ApiError> {
+        self.post("/api/sleep", request, true)
+    }
+
+    /// `POST /api/auth/register`, not retried as the account may already have
+    /// been created.
+    pub fn register(&self, request: &RegisterRequest) -> Result<AuthResponse, ApiError> {
+        self.post("/api/auth/register", request, false)
+    }
+
+    /// `POST /api/auth/login`
+    pub fn login(&self, request: &LoginRequest) -> Result<AuthResponse, ApiError> {
+        self.post("/api/auth/login", request, true)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        self.send(self.http.get(&url).query(query), true)
+    }
+
+    // `retryable` is false for requests that must not be applied twice, such
+    // as adding to a total: a timeout doesn't tell whether the server did it
+    fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B, retryable: bool) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        self.send(self.http.post(&url).json(body), retryable)
+    }
+
+    fn send<T: DeserializeOwned>(&self, mut request: RequestBuilder, retryable: bool) -> Result<T, ApiError> {
+        if !self.token.is_empty() {
+            request = match self.auth {
+                AuthMode::Header => request.bearer_auth(&self.token),
+                AuthMode::Query => request.query(&[("token", self.token.as_str())]),
+            };
+        }
+
+        let max_attempts = if retryable { self.retry.max_attempts } else { 1 };
+        let mut retry = 0;
+        loop {
+            self.breaker
+                .lock()
+                .unwrap()
+                .check()
+                .map_err(|retry_in| ApiError::CircuitOpen { retry_in })?;
+
+            // Bodies are always buffered JSON, so requests can be cloned
+            let attempt = request.try_clone().expect("request body is not cloneable").timeout(self.timeout);
+            let result = attempt
+                .send()
+                .map_err(|e| ApiError::Request(redact_error(e)))
+                .and_then(|response| self.parse_response(response));
+
+            match result {
+                Err(e) if e.is_transient() => {
+                    if retry + 1 >= max_attempts {
+                        self.breaker.lock().unwrap().record_failure();
+                        return Err(e);
+                    }
+                    thread::sleep(self.retry.delay(retry));
+                    retry += 1;
+                }
+                result => {
+                    // The server answered, even if it was to refuse the request
+                    self.breaker.lock().unwrap().record_success();
+                    return result;
+                }
+            }
+        }
+    }
+
+    fn parse_response<T: DeserializeOwned>(&self, response: Response) -> Result<T, ApiError> {
+        let status = response.status();
+        if status.is_success() {
+            return response.json().map_err(|e| ApiError::Decode(redact_error(e)));
+        }
+
+        // Try to parse error response, fallback to status code
+        let message = match response.json::<ErrorResponse>() {
+            Ok(error_response) => self.redact(&error_response.error),
+            Err(_) => status.canonical_reason().unwrap_or("Unknown").to_string(),
+        };
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(ApiError::Unauthorized { message });
+        }
+        Err(ApiError::Status { status, message })
+    }
+}
+
+// Strip the token query parameter from the URL reqwest embeds in its errors
+fn redact_error(mut error: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = error.url_mut() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if k == "token" { REDACTED.to_string() } else { v.into_owned() };
+                (k.into_owned(), v)
+            })
+            .collect();
+        if !pairs.is_empty() {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    error
+}
@@ -0,0 +1,58 @@
+use discord_rpc::mock::MockApi;
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "mock-api", version, about = "Serve fake step tracker API data for offline development")]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, short, default_value = "127.0.0.1:8080")]
+    bind: String,
+    /// Milliseconds to wait before every response
+    #[arg(long, default_value_t = 0)]
+    latency: u64,
+    /// Fraction of requests (0.0 to 1.0) answered with HTTP 500
+    #[arg(long, default_value_t = 0.0)]
+    failure_rate: f64,
+    /// Token required on every endpoint except login and register
+    #[arg(long)]
+    token: Option<String>,
+    /// TOML file of scripted responses
+    #[arg(long, short, value_name = "FILE")]
+    script: Option<PathBuf>,
+    /// Don't print a line per request
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let mock = match MockApi::start(&cli.bind) {
+        Ok(mock) => mock,
+        Err(e) => {
+            eprintln!("❌ Could not listen on {}: {}", cli.bind, e);
+            process::exit(1);
+        }
+    };
+    if let Some(ref path) = cli.script {
+        let loaded = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|script| mock.load_script(&script).map_err(|e| e.to_string()));
+        if let Err(e) = loaded {
+            eprintln!("❌ Invalid script {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+    mock.set_latency(Duration::from_millis(cli.latency));
+    mock.set_failure_rate(cli.failure_rate);
+    mock.require_token(cli.token.as_deref());
+    mock.set_logging(!cli.quiet);
+
+    println!("🧪 Mock API listening on {}", mock.url());
+    println!("   Run the daemon with API_URL={}", mock.url());
+    mock.wait();
+}
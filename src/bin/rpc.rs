@@ -1,762 +1,687 @@
+use discord_rpc::cache::Cache;
+use discord_rpc::metrics::Metric;
 use discord_rpc::models::*;
-use discord_rpc_client::{Client, Event};
-use reqwest;
-use std::{env, thread, time::Duration};
+use discord_rpc::presence::{Activity, DiscordPresence, DryRunPresence, Presence};
+use discord_rpc::rotation::{Rotation, StatusOutput};
+use discord_rpc::streak::{self, PastDays, Streak};
+use discord_rpc::{control, credentials, obs, Achievements, ApiClient, ApiError, Config, ConfigError, Error, History};
+use clap::{Parser, Subcommand};
+use std::{thread, time::Duration};
 use std::fs;
-use chrono::{Local, TimeZone, Datelike};
-
-// Get API URL from environment variable
-fn get_api_url() -> String {
-    env::var("API_URL")
-        .expect("API_URL must be set in .env file")
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{Duration as Days, Local, NaiveDate};
+
+// Set by --verbose
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// Print only when --verbose is given, on stderr so it never mixes with
+// command output such as dry-run JSON
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if VERBOSE.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
 }
 
-// Get Steps Discord Client ID from environment variable
-fn get_steps_discord_client_id() -> u64 {
-    env::var("STEPS_DISCORD_CLIENT_ID")
-        .expect("STEPS_DISCORD_CLIENT_ID must be set in .env file")
-        .parse()
-        .expect("STEPS_DISCORD_CLIENT_ID must be a valid u64")
+#[derive(Parser)]
+#[command(name = "rpc", version, about = "Show your step tracker stats as Discord Rich Presence")]
+struct Cli {
+    /// Configuration file (defaults to ./config.toml, then the user config directory)
+    #[arg(long, short, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Print extra diagnostic output
+    #[arg(long, short, global = true)]
+    verbose: bool,
+    /// Fetch and render without connecting to Discord or submitting data
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-// Get Steps image key from environment variable
-fn get_steps_large_image_key() -> String {
-    env::var("STEPS_DISCORD_LARGE_IMAGE_KEY")
-        .expect("STEPS_DISCORD_LARGE_IMAGE_KEY must be set in .env file")
+#[derive(Subcommand)]
+enum Command {
+    /// Run the presence daemon (the default)
+    Run,
+    /// Fetch every enabled metric once, show one of them, then exit
+    Once {
+        /// Metric to show on Discord (defaults to the first enabled one)
+        #[arg(long)]
+        metric: Option<String>,
+        /// Seconds to keep the presence visible before exiting
+        #[arg(long, default_value_t = 15)]
+        hold: u64,
+    },
+    /// Show daemon, login and configuration status
+    Status,
+    /// Validate the configuration and print the resolved settings
+    CheckConfig,
+    /// Fetch every enabled metric and print it as JSON
+    Export {
+        /// Write to a file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Show values recorded in the local history
+    History {
+        /// Only show this metric
+        #[arg(long)]
+        metric: Option<String>,
+        /// Show a single day (YYYY-MM-DD) in detail
+        #[arg(long, value_parser = parse_date)]
+        date: Option<String>,
+        /// Number of days to show, ending today
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// List unlocked achievements and personal bests
+    Achievements,
+    /// Log in to the step tracker API and store the token
+    Login,
+    /// Create a step tracker account and store the token
+    Register,
+    /// Remove the stored token
+    Logout,
+    /// Submit steps manually
+    Steps {
+        #[command(subcommand)]
+        action: StepsAction,
+    },
 }
 
-// Get Water Discord Client ID from environment variable
-fn get_water_discord_client_id() -> u64 {
-    env::var("WATER_DISCORD_CLIENT_ID")
-        .expect("WATER_DISCORD_CLIENT_ID must be set in .env file")
-        .parse()
-        .expect("WATER_DISCORD_CLIENT_ID must be a valid u64")
+#[derive(Subcommand)]
+enum StepsAction {
+    /// Add steps to the day's total
+    Add {
+        steps: i64,
+        /// Day to update (YYYY-MM-DD), defaults to today
+        #[arg(long, value_parser = parse_date)]
+        date: Option<String>,
+    },
+    /// Replace the day's total
+    Set {
+        steps: i64,
+        /// Day to update (YYYY-MM-DD), defaults to today
+        #[arg(long, value_parser = parse_date)]
+        date: Option<String>,
+    },
 }
 
-// Get Water image key from environment variable
-fn get_water_large_image_key() -> String {
-    env::var("WATER_DISCORD_LARGE_IMAGE_KEY")
-        .expect("WATER_DISCORD_LARGE_IMAGE_KEY must be set in .env file")
+fn parse_date(s: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("'{}' is not a YYYY-MM-DD date", s))
 }
 
-// Get Sleep Discord Client ID from environment variable
-fn get_sleep_discord_client_id() -> u64 {
-    env::var("SLEEP_DISCORD_CLIENT_ID")
-        .expect("SLEEP_DISCORD_CLIENT_ID must be set in .env file")
-        .parse()
-        .expect("SLEEP_DISCORD_CLIENT_ID must be a valid u64")
+// Build an API client from the configuration, optionally without a token
+fn api_client(config: &Config, authenticated: bool) -> Result<ApiClient, Error> {
+    let token = if authenticated {
+        config.api_token()?.ok_or(Error::MissingToken)?
+    } else {
+        String::new()
+    };
+    let url = config
+        .api_url()
+        .map_err(|problem| ConfigError::Invalid(vec![problem]))?;
+    let api = ApiClient::new(url, token)?
+        .with_auth_mode(config.api.auth)
+        .with_retry_policy(config.retry.policy())
+        .with_circuit_breaker(config.retry.circuit_breaker());
+    Ok(api)
 }
 
-// Get Sleep image key from environment variable
-fn get_sleep_large_image_key() -> String {
-    env::var("SLEEP_DISCORD_LARGE_IMAGE_KEY")
-        .expect("SLEEP_DISCORD_LARGE_IMAGE_KEY must be set in .env file")
+// Print a prompt and read one trimmed line from stdin
+fn prompt(label: &str) -> io::Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
 }
 
-// Check if steps activity is enabled (default: true)
-fn is_steps_enabled() -> bool {
-    env::var("ENABLE_STEPS")
-        .unwrap_or_else(|_| "true".to_string())
-        .to_lowercase()
-        == "true"
+// Store a freshly issued token and report where it went
+fn store_token(auth: &AuthResponse) -> Result<(), Box<dyn std::error::Error>> {
+    let path = credentials::save_token(&auth.token)?;
+    println!("✅ Token saved to {}", path.display());
+    Ok(())
 }
 
-// Check if water activity is enabled (default: true)
-fn is_water_enabled() -> bool {
-    env::var("ENABLE_WATER")
-        .unwrap_or_else(|_| "true".to_string())
-        .to_lowercase()
-        == "true"
-}
+fn login(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let api = api_client(config, false)?;
+    let username = prompt("Username: ")?;
+    let password = rpassword::prompt_password("Password: ")?;
 
-// Check if sleep activity is enabled (default: true)
-fn is_sleep_enabled() -> bool {
-    env::var("ENABLE_SLEEP")
-        .unwrap_or_else(|_| "true".to_string())
-        .to_lowercase()
-        == "true"
+    let auth = api.login(&LoginRequest { username, password })?;
+    println!("✅ Logged in to {}", api.base_url());
+    store_token(&auth)
 }
 
-// Get output file paths for OBS (optional)
-fn get_obs_steps_file() -> Option<String> {
-    env::var("OBS_STEPS_FILE").ok()
-}
+fn register(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let api = api_client(config, false)?;
+    let username = prompt("Username: ")?;
+    let password = rpassword::prompt_password("Password: ")?;
+    let confirm = rpassword::prompt_password("Confirm password: ")?;
+    if password != confirm {
+        return Err("passwords do not match".into());
+    }
 
-fn get_obs_water_file() -> Option<String> {
-    env::var("OBS_WATER_FILE").ok()
+    let auth = api.register(&RegisterRequest { username, password })?;
+    println!("✅ Registered on {}", api.base_url());
+    store_token(&auth)
 }
 
-fn get_obs_sleep_file() -> Option<String> {
-    env::var("OBS_SLEEP_FILE").ok()
+fn logout(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    credentials::delete_token()?;
+    if let Some(cache) = open_cache(config) {
+        cache.clear()?;
+    }
+    println!("✅ Stored token and cached data removed");
+    Ok(())
 }
 
-// Write steps data to text file for OBS
-fn write_obs_steps_file(
-    steps_data: &StepsSummaryResponse,
-    file_path: &str,
-) {
-    let text = format!(
-        "Today: {}\nMonthly: {}\nYearly: {}",
-        format_number(steps_data.daily),
-        format_number(steps_data.monthly),
-        format_number(steps_data.yearly)
+fn submit_steps(config: &Config, action: StepsAction, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let api = api_client(config, true)?;
+    if dry_run {
+        let (endpoint, steps, date) = match action {
+            StepsAction::Add { steps, date } => ("/api/steps/add", steps, date),
+            StepsAction::Set { steps, date } => ("/api/steps", steps, date),
+        };
+        let body = serde_json::to_string(&StepsRequest { steps, date })?;
+        println!("[dry-run] POST {}{} {}", api.base_url(), endpoint, body);
+        return Ok(());
+    }
+
+    let response = match action {
+        StepsAction::Add { steps, date } => api.add_steps(&StepsRequest { steps, date })?,
+        StepsAction::Set { steps, date } => api.set_steps(&StepsRequest { steps, date })?,
+    };
+
+    println!(
+        "✅ Steps for {}: {}",
+        response.date.as_deref().unwrap_or("today"),
+        response.steps
     );
 
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = std::path::Path::new(file_path).parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("Failed to create directory for OBS steps file: {}", e);
-            return;
-        }
-    }
-    
-    if let Err(e) = fs::write(file_path, text) {
-        eprintln!("Failed to write OBS steps file: {}", e);
-    } else {
-        println!("✅ OBS steps data written to {}", file_path);
+    if control::request_refresh()? {
+        println!("🔄 Asked the running RPC daemon to refresh");
     }
+    Ok(())
 }
 
-// Write water data to text file for OBS
-fn write_obs_water_file(
-    water_data: &WaterSummaryResponse,
-    file_path: &str,
-) {
-    let text = format!(
-        "Today: {}\nMonthly: {}\nYearly: {}",
-        water_data.daily_display,
-        water_data.monthly_display,
-        water_data.yearly_display
-    );
+fn check_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    match config.source {
+        Some(ref path) => println!("Configuration file: {}", path.display()),
+        None => println!("Configuration file: none (environment only)"),
+    }
+    config.validate()?;
+
+    println!("API: {} (auth: {:?})", config.api_url()?, config.api.auth);
+    let token_source = if config.api.token.is_some() {
+        "configured"
+    } else if credentials::load_token()?.is_some() {
+        "stored by `rpc login`"
+    } else {
+        "missing"
+    };
+    println!("Token: {}", token_source);
+    match config.single_client_id() {
+        Some(client_id) => println!("Discord: single application {}", client_id),
+        None => println!("Discord: one application per metric"),
+    }
+    if let Some(ref path) = config.discord.ipc_path {
+        println!("Discord IPC socket: {}", path.display());
+    }
+    match config.cache.path() {
+        Some(path) => println!("Cache: {}", path.display()),
+        None => println!("Cache: disabled"),
+    }
+    match config.history.path() {
+        Some(path) => println!("History: {}", path.display()),
+        None => println!("History: disabled"),
+    }
+    match config.achievements.path() {
+        Some(path) => println!(
+            "Achievements: {} ({} milestones)",
+            path.display(),
+            config.achievements.milestones()?.len()
+        ),
+        None => println!("Achievements: disabled"),
+    }
 
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = std::path::Path::new(file_path).parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("Failed to create directory for OBS water file: {}", e);
-            return;
+    for metric in config.enabled_metrics() {
+        let options = metric.options();
+        println!(
+            "{}: client {}, image '{}', hover '{}'{}",
+            metric.label(),
+            config.single_client_id().unwrap_or(options.client_id),
+            options.image_key,
+            options.hover_text,
+            options
+                .obs_file
+                .as_ref()
+                .map(|file| format!(", OBS {}", file.display()))
+                .unwrap_or_default()
+        );
+        let templates = &options.templates;
+        for (field, template) in [
+            ("details", &templates.details),
+            ("state", &templates.state),
+            ("hover_text", &templates.hover_text),
+            ("obs", &templates.obs),
+        ] {
+            if let Some(template) = template {
+                println!("  {} template: {}", field, template.source());
+            }
+        }
+        let badges = &options.badges;
+        for (state, badge) in [
+            ("stale", &badges.stale),
+            ("goal_met", &badges.goal_met),
+            ("streak", &badges.streak),
+            ("default", &badges.default),
+        ] {
+            if let Some(badge) = badge {
+                println!("  {} badge: '{}'", state, badge.image_key);
+            }
+        }
+        for button in &options.buttons {
+            println!("  button '{}' -> {}", button.label, button.url);
         }
     }
-    
-    if let Err(e) = fs::write(file_path, text) {
-        eprintln!("Failed to write OBS water file: {}", e);
-    } else {
-        println!("✅ OBS water data written to {}", file_path);
+    println!("✅ Configuration is valid");
+    Ok(())
+}
+
+fn status(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    match control::running_pid() {
+        Some(pid) => println!("Daemon: running (pid {})", pid),
+        None => println!("Daemon: not running"),
+    }
+    match credentials::load_token()? {
+        Some(_) => println!("Login: token stored in {}", credentials::token_path().unwrap_or_default().display()),
+        None => println!("Login: no stored token"),
+    }
+    match config.source {
+        Some(ref path) => println!("Configuration: {}", path.display()),
+        None => println!("Configuration: environment only"),
     }
+    match config.validate() {
+        Ok(()) => {
+            let metrics: Vec<String> = config.enabled_metrics().iter().map(|m| m.id().to_string()).collect();
+            println!("Enabled metrics: {}", metrics.join(", "));
+        }
+        Err(e) => println!("⚠️  {}", e),
+    }
+    Ok(())
 }
 
-// Write sleep data to text file for OBS
-fn write_obs_sleep_file(
-    sleep_data: &SleepResponse,
-    file_path: &str,
-) {
-    let formatted = format_sleep_minutes(sleep_data.daily_minutes);
-    let text = format!("Today: {}", formatted);
-
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = std::path::Path::new(file_path).parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("Failed to create directory for OBS sleep file: {}", e);
-            return;
+fn export(config: &Config, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    config.validate()?;
+    let api = api_client(config, true)?;
+
+    let mut data = serde_json::Map::new();
+    for mut metric in config.enabled_metrics() {
+        verbose!("Fetching {}...", metric.id());
+        metric.fetch(&api)?;
+        data.insert(metric.id().to_string(), metric.snapshot().unwrap_or_default());
+    }
+
+    let json = serde_json::to_string_pretty(&data)?;
+    match output {
+        Some(path) => {
+            fs::write(path, json)?;
+            println!("✅ Exported to {}", path.display());
         }
+        None => println!("{}", json),
     }
-    
-    if let Err(e) = fs::write(file_path, text) {
-        eprintln!("Failed to write OBS sleep file: {}", e);
-    } else {
-        println!("✅ OBS sleep data written to {}", file_path);
+    Ok(())
+}
+
+// The cache of last-known-good data, if enabled
+fn open_cache(config: &Config) -> Option<Cache> {
+    config.cache.path().map(Cache::new)
+}
+
+// Load the last-known-good data of each metric, to fall back on until it is fetched
+fn restore_cached(cache: Option<&Cache>, metrics: &mut [Box<dyn Metric>]) {
+    let Some(cache) = cache else {
+        return;
+    };
+    for metric in metrics.iter_mut() {
+        match cache.restore(metric.as_mut()) {
+            Ok(Some(fetched_at)) => verbose!("Restored cached {} data from {}", metric.id(), fetched_at.format("%Y-%m-%d %H:%M")),
+            Ok(None) => {}
+            Err(e) => eprintln!("Ignoring cached {} data: {}", metric.id(), e),
+        }
     }
 }
 
-// Format minutes as hours and minutes
-fn format_sleep_minutes(minutes: i64) -> String {
-    let hours = minutes / 60;
-    let mins = minutes % 60;
-    if hours > 0 {
-        if mins > 0 {
-            format!("{}h {}m", hours, mins)
-        } else {
-            format!("{}h", hours)
+// Open the local history, carrying on without it if that fails
+fn open_history(config: &Config) -> Option<History> {
+    let path = config.history.path()?;
+    match History::open(&path) {
+        Ok(history) => {
+            verbose!("Recording history in {}", path.display());
+            Some(history)
+        }
+        Err(e) => {
+            eprintln!("⚠️  History disabled, could not open {}: {}", path.display(), e);
+            None
         }
-    } else {
-        format!("{}m", mins)
     }
 }
 
-// Get today's date in YYYY-MM-DD format
-fn get_today_date() -> String {
-    Local::now().format("%Y-%m-%d").to_string()
+// Open the saved achievements, carrying on without them if that fails
+fn open_achievements(config: &Config) -> Option<Achievements> {
+    let path = config.achievements.path()?;
+    let milestones = match config.achievements.milestones() {
+        Ok(milestones) => milestones,
+        Err(e) => {
+            eprintln!("⚠️  Achievements disabled: {}", e);
+            return None;
+        }
+    };
+    match Achievements::open(&path) {
+        Ok(achievements) => {
+            verbose!("Saving achievements in {}", path.display());
+            Some(
+                achievements
+                    .with_milestones(milestones)
+                    .with_duration(config.achievements.duration)
+                    .with_image_key(config.achievements.image_key.clone()),
+            )
+        }
+        Err(e) if matches!(e.kind(), io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof) => {
+            eprintln!("⚠️  Achievements disabled, {} is malformed: {}", path.display(), e);
+            None
+        }
+        Err(e) => {
+            eprintln!("⚠️  Achievements disabled, could not open {}: {}", path.display(), e);
+            None
+        }
+    }
 }
 
-// Calculate total minutes since the start of the year
-fn get_minutes_since_year_start() -> i64 {
-    let now = Local::now();
-    let year_start = Local.with_ymd_and_hms(now.year(), 1, 1, 0, 0, 0)
-        .single()
-        .expect("Failed to create year start");
-    let duration = now.signed_duration_since(year_start);
-    duration.num_minutes()
+fn achievements(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.achievements.path().ok_or("achievements are disabled")?;
+    let achievements = Achievements::open(&path)?;
+
+    if achievements.unlocked().is_empty() {
+        println!("No achievements unlocked yet");
+    }
+    for unlock in achievements.unlocked() {
+        println!("🏆 {}  {} ({})", unlock.unlocked_at.format("%Y-%m-%d"), unlock.title, unlock.period);
+    }
+    for metric in config.enabled_metrics() {
+        if let Some(best) = achievements.personal_best(metric.id()) {
+            println!(
+                "{} best day: {} on {}",
+                metric.label(),
+                metric.format_value(best.value),
+                best.date.format("%a %Y-%m-%d")
+            );
+        }
+    }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load environment variables from .env file
-    dotenv::dotenv().ok();
+fn history(config: &Config, selected: Option<&str>, date: Option<&str>, days: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.history.path().ok_or("history is disabled")?;
+    if !path.exists() {
+        println!("No history recorded yet ({} does not exist)", path.display());
+        return Ok(());
+    }
+    let history = History::open(&path)?;
 
-    // Set a panic hook to catch panics from Discord RPC background threads
-    // Note: This won't prevent crashes in background threads, but will log them
-    std::panic::set_hook(Box::new(|panic_info| {
-        let message = if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
-            s.to_string()
-        } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
-            s.clone()
-        } else {
-            format!("{:?}", panic_info)
-        };
-        
-        if message.contains("Socket is not connected") || message.contains("NotConnected") {
-            eprintln!("⚠️  Discord RPC connection lost (socket not connected). This is usually harmless and will be handled by reconnection logic.");
-        } else {
-            eprintln!("⚠️  Panic in Discord RPC: {}", message);
+    let mut metrics = config.enabled_metrics();
+    if let Some(id) = selected {
+        metrics.retain(|metric| metric.id() == id);
+        if metrics.is_empty() {
+            return Err(format!("metric '{}' is not enabled", id).into());
         }
-    }));
-
-    // Get token from environment variable
-    let token = env::var("API_TOKEN")
-        .expect("API_TOKEN must be set in .env file");
-
-    // Get configuration from environment
-    let api_url = get_api_url();
-    let steps_discord_client_id = get_steps_discord_client_id();
-    let steps_large_image_key = get_steps_large_image_key();
-    let water_discord_client_id = get_water_discord_client_id();
-    let water_large_image_key = get_water_large_image_key();
-    let sleep_discord_client_id = get_sleep_discord_client_id();
-    let sleep_large_image_key = get_sleep_large_image_key();
-    let steps_enabled = is_steps_enabled();
-    let water_enabled = is_water_enabled();
-    let sleep_enabled = is_sleep_enabled();
-    let obs_steps_file = get_obs_steps_file();
-    let obs_water_file = get_obs_water_file();
-    let obs_sleep_file = get_obs_sleep_file();
-
-    println!("Connecting to API: {}", api_url);
-    println!("Using Steps Discord Client ID: {} (enabled: {})", steps_discord_client_id, steps_enabled);
-    println!("Using Water Discord Client ID: {} (enabled: {})", water_discord_client_id, water_enabled);
-    println!("Using Sleep Discord Client ID: {} (enabled: {})", sleep_discord_client_id, sleep_enabled);
-    if let Some(ref file) = obs_steps_file {
-        println!("OBS steps file: {}", file);
-    }
-    if let Some(ref file) = obs_water_file {
-        println!("OBS water file: {}", file);
-    }
-    if let Some(ref file) = obs_sleep_file {
-        println!("OBS sleep file: {}", file);
-    }
-
-    // Main loop with reconnection logic - alternate between steps, water, and sleep
-    loop {
-        // Run all RPC clients, alternating updates
-        match run_triple_rpc_clients(
-            &api_url,
-            &token,
-            steps_discord_client_id,
-            &steps_large_image_key,
-            steps_enabled,
-            water_discord_client_id,
-            &water_large_image_key,
-            water_enabled,
-            sleep_discord_client_id,
-            &sleep_large_image_key,
-            sleep_enabled,
-            obs_steps_file.as_deref(),
-            obs_water_file.as_deref(),
-            obs_sleep_file.as_deref(),
-        ) {
-            Ok(_) => {
-                eprintln!("RPC clients exited normally. Restarting in 5 seconds...");
-                thread::sleep(Duration::from_secs(5));
-            }
-            Err(e) => {
-                eprintln!("RPC client error: {}. Restarting in 5 seconds...", e);
-                thread::sleep(Duration::from_secs(5));
+    }
+
+    if let Some(date) = date {
+        let date: NaiveDate = date.parse()?;
+        for metric in &metrics {
+            let entries = history.entries(metric.id(), date)?;
+            let Some(last) = entries.last() else {
+                println!("{} on {}: no data", metric.label(), date.format("%a %Y-%m-%d"));
+                continue;
+            };
+            println!(
+                "{} on {}: {} (month {}, year {}, as of {})",
+                metric.label(),
+                date.format("%a %Y-%m-%d"),
+                metric.format_value(last.totals.daily),
+                metric.format_value(last.totals.monthly),
+                metric.format_value(last.totals.yearly),
+                last.fetched_at.format("%H:%M")
+            );
+            for entry in &entries {
+                verbose!("  {}  {}", entry.fetched_at.format("%H:%M:%S"), metric.format_value(entry.totals.daily));
             }
         }
+        return Ok(());
     }
-}
 
-fn run_triple_rpc_clients(
-    api_url: &str,
-    token: &str,
-    steps_discord_client_id: u64,
-    steps_large_image_key: &str,
-    steps_enabled: bool,
-    water_discord_client_id: u64,
-    water_large_image_key: &str,
-    water_enabled: bool,
-    sleep_discord_client_id: u64,
-    sleep_large_image_key: &str,
-    sleep_enabled: bool,
-    obs_steps_file: Option<&str>,
-    obs_water_file: Option<&str>,
-    obs_sleep_file: Option<&str>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Only create clients if they're enabled
-    let mut steps_drpc_opt = if steps_enabled {
-        let mut drpc = Client::new(steps_discord_client_id);
-        drpc.on_ready(|_ctx| {
-            println!("Steps Discord RPC connected!");
-        });
-        drpc.on_event(Event::Ready, |_ctx| {
-            println!("Steps Discord RPC ready!");
-        });
-        drpc.start();
-        Some(drpc)
-    } else {
-        println!("Steps RPC is disabled");
-        None
-    };
+    let to = Local::now().date_naive();
+    let from = to - Days::days(i64::from(days.max(1)) - 1);
+    for metric in &metrics {
+        println!("{}", metric.label());
+        let entries = history.days(metric.id(), from, to)?;
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            let value = entries
+                .iter()
+                .find(|entry| entry.date == day)
+                .map(|entry| metric.format_value(entry.totals.daily))
+                .unwrap_or_else(|| "-".to_string());
+            println!("  {}  {}", day.format("%a %Y-%m-%d"), value);
+        }
+        if let Some(goal) = metric.options().goal {
+            let days = history.days(metric.id(), to - Days::days(streak::WINDOW_DAYS), to)?;
+            let streak = Streak::from_days(days.iter().map(|entry| (entry.date, entry.totals.daily)), goal, to);
+            println!(
+                "  🔥 Current streak: {} days, longest: {} (goal {})",
+                streak.current,
+                streak.longest,
+                metric.format_value(goal)
+            );
+        }
+    }
+    Ok(())
+}
 
-    let mut water_drpc_opt = if water_enabled {
-        let mut drpc = Client::new(water_discord_client_id);
-        drpc.on_ready(|_ctx| {
-            println!("Water Discord RPC connected!");
-        });
-        drpc.on_event(Event::Ready, |_ctx| {
-            println!("Water Discord RPC ready!");
-        });
-        drpc.start();
-        Some(drpc)
-    } else {
-        println!("Water RPC is disabled");
-        None
+// Connect to Discord in the configured mode
+fn discord_presence(config: &Config, metrics: &[Box<dyn Metric>]) -> DiscordPresence {
+    let mut presence = match config.single_client_id() {
+        Some(client_id) => DiscordPresence::new_single(client_id, config.timing.connect_wait),
+        None => DiscordPresence::new(metrics, config.timing.connect_wait),
     };
+    if let Some(ref path) = config.discord.ipc_path {
+        presence = presence.with_socket_path(path);
+    }
+    presence.connect();
+    presence
+}
 
-    let mut sleep_drpc_opt = if sleep_enabled {
-        let mut drpc = Client::new(sleep_discord_client_id);
-        drpc.on_ready(|_ctx| {
-            println!("Sleep Discord RPC connected!");
-        });
-        drpc.on_event(Event::Ready, |_ctx| {
-            println!("Sleep Discord RPC ready!");
-        });
-        drpc.start();
-        Some(drpc)
-    } else {
-        println!("Sleep RPC is disabled");
-        None
-    };
+fn dry_run_presence(config: &Config, metrics: &[Box<dyn Metric>]) -> DryRunPresence<io::Stdout> {
+    let presence = DryRunPresence::stdout(metrics);
+    match config.single_client_id() {
+        Some(client_id) => presence.single(client_id),
+        None => presence,
+    }
+}
 
-    // Give Discord RPC a moment to connect
-    thread::sleep(Duration::from_secs(2));
-
-    // Determine which activity to show first
-    // Cycle through: steps -> water -> sleep -> repeat
-    // Start with the first enabled activity
-    let mut current_activity = if steps_enabled {
-        0 // steps
-    } else if water_enabled {
-        1 // water
-    } else if sleep_enabled {
-        2 // sleep
-    } else {
-        // All disabled - just wait
-        loop {
-            thread::sleep(Duration::from_secs(60));
-        }
+fn once(config: &Config, selected: Option<&str>, hold: u64, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    config.validate()?;
+    let api = api_client(config, true)?;
+    let mut metrics = config.enabled_metrics();
+
+    let slot = match selected {
+        Some(id) => metrics
+            .iter()
+            .position(|metric| metric.id() == id)
+            .ok_or_else(|| format!("metric '{}' is not enabled", id))?,
+        None if metrics.is_empty() => return Err("all metrics are disabled".into()),
+        None => 0,
     };
 
-    // Main loop: cycle through steps -> water -> sleep -> repeat
-    loop {
-        match current_activity {
-            0 if steps_enabled => {
-                println!("🔄 Switching to Steps RPC...");
-                
-                match fetch_steps_summary(api_url, token) {
-                    Ok(summary) => {
-                        let (start_timestamp, end_timestamp) = get_day_timestamps();
-                        let details = format!("Today: {}", format_number(summary.daily));
-                        let state = format!(
-                            "Monthly: {} | Yearly: {}",
-                            format_number(summary.monthly),
-                            format_number(summary.yearly)
-                        );
-                        
-                        println!(
-                            "Fetched steps - Today: {}, Monthly: {}, Yearly: {}",
-                            summary.daily, summary.monthly, summary.yearly
-                        );
-                        
-                        if let Some(file_path) = obs_steps_file {
-                            write_obs_steps_file(&summary, file_path);
-                        }
-
-                        if let Some(ref mut steps_drpc) = steps_drpc_opt {
-                            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                steps_drpc.set_activity(|act| {
-                                    let mut activity = act.state(&state)
-                                        .details(&details)
-                                        .timestamps(|timestamps| {
-                                            timestamps.start(start_timestamp).end(end_timestamp)
-                                        });
-                                    
-                                    if !steps_large_image_key.is_empty() {
-                                        activity = activity.assets(|assets| {
-                                            assets.large_image(steps_large_image_key)
-                                                .large_text("I'm walking here!")
-                                        });
-                                    }
-                                    
-                                    activity
-                                })
-                            }));
-
-                            match result {
-                                Ok(Ok(_)) => {
-                                    println!("✅ Steps activity set successfully");
-                                    // Clear other activities
-                                    if let Some(ref mut water_drpc) = water_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = water_drpc.clear_activity();
-                                        }));
-                                    }
-                                    if let Some(ref mut sleep_drpc) = sleep_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = sleep_drpc.clear_activity();
-                                        }));
-                                    }
-                                    // Move to next activity
-                                    current_activity = get_next_activity(1, steps_enabled, water_enabled, sleep_enabled);
-                                }
-                                Ok(Err(e)) => {
-                                    eprintln!("Failed to set steps activity: {}", e);
-                                    return Err(format!("Steps Discord RPC connection lost: {}", e).into());
-                                }
-                                Err(_) => {
-                                    eprintln!("Panic caught while setting steps activity. Reconnecting...");
-                                    return Err("Panic in set_activity".into());
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error fetching steps: {}", e);
-                        if let Some(ref mut steps_drpc) = steps_drpc_opt {
-                            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                steps_drpc.set_activity(|act| {
-                                    act.state("Unable to fetch steps").details("API connection error")
-                                })
-                            }));
-                        }
-                        current_activity = get_next_activity(1, steps_enabled, water_enabled, sleep_enabled);
-                    }
-                }
-            }
-            1 if water_enabled => {
-                println!("🔄 Switching to Water RPC...");
-                
-                match fetch_water_summary(api_url, token) {
-                    Ok(summary) => {
-                        let (start_timestamp, end_timestamp) = get_day_timestamps();
-                        let details = format!("Today: {}", summary.daily_display);
-                        let state = format!(
-                            "Monthly: {} | Yearly: {}",
-                            summary.monthly_display,
-                            summary.yearly_display
-                        );
-                        
-                        println!(
-                            "Fetched water - Today: {}, Monthly: {}, Yearly: {}",
-                            summary.daily_display, summary.monthly_display, summary.yearly_display
-                        );
-                        
-                        if let Some(file_path) = obs_water_file {
-                            write_obs_water_file(&summary, file_path);
-                        }
-
-                        if let Some(ref mut water_drpc) = water_drpc_opt {
-                            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                water_drpc.set_activity(|act| {
-                                    let mut activity = act.state(&state)
-                                        .details(&details)
-                                        .timestamps(|timestamps| {
-                                            timestamps.start(start_timestamp).end(end_timestamp)
-                                        });
-                                    
-                                    if !water_large_image_key.is_empty() {
-                                        activity = activity.assets(|assets| {
-                                            assets.large_image(water_large_image_key)
-                                                .large_text("Staying hydrated!")
-                                        });
-                                    }
-                                    
-                                    activity
-                                })
-                            }));
-
-                            match result {
-                                Ok(Ok(_)) => {
-                                    println!("✅ Water activity set successfully");
-                                    // Clear other activities
-                                    if let Some(ref mut steps_drpc) = steps_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = steps_drpc.clear_activity();
-                                        }));
-                                    }
-                                    if let Some(ref mut sleep_drpc) = sleep_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = sleep_drpc.clear_activity();
-                                        }));
-                                    }
-                                    // Move to next activity
-                                    current_activity = get_next_activity(2, steps_enabled, water_enabled, sleep_enabled);
-                                }
-                                Ok(Err(e)) => {
-                                    eprintln!("Failed to set water activity: {}", e);
-                                    return Err(format!("Water Discord RPC connection lost: {}", e).into());
-                                }
-                                Err(_) => {
-                                    eprintln!("Panic caught while setting water activity. Reconnecting...");
-                                    return Err("Panic in set_activity".into());
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error fetching water: {}", e);
-                        if let Some(ref mut water_drpc) = water_drpc_opt {
-                            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                water_drpc.set_activity(|act| {
-                                    act.state("Unable to fetch water").details("API connection error")
-                                })
-                            }));
-                        }
-                        current_activity = get_next_activity(2, steps_enabled, water_enabled, sleep_enabled);
+    let status = status_output(dry_run);
+    let cache = open_cache(config);
+    restore_cached(cache.as_ref(), &mut metrics);
+    // Dry runs must not add to the history that streaks are counted from
+    let history = if dry_run { None } else { open_history(config) };
+    let mut fetched = vec![false; metrics.len()];
+    for (metric, fetched) in metrics.iter_mut().zip(fetched.iter_mut()) {
+        match metric.fetch(&api) {
+            Ok(()) => {
+                *fetched = true;
+                status.print(format_args!("Fetched {} - {} | {}", metric.id(), metric.details(), metric.state()));
+                // Dry runs read the cache but leave it as it was
+                if let Some(cache) = cache.as_ref().filter(|_| !dry_run) {
+                    if let Err(e) = cache.store(metric.as_ref()) {
+                        eprintln!("Failed to cache {} data: {}", metric.id(), e);
                     }
                 }
-            }
-            2 if sleep_enabled => {
-                println!("🔄 Switching to Sleep RPC...");
-                
-                let today_date = get_today_date();
-                match fetch_sleep(api_url, token, &today_date) {
-                    Ok(sleep_data) => {
-                        let (start_timestamp, end_timestamp) = get_day_timestamps();
-                        let daily_formatted = format_sleep_minutes(sleep_data.daily_minutes);
-                        let monthly_formatted = format_sleep_minutes(sleep_data.monthly_minutes);
-                        let yearly_formatted = format_sleep_minutes(sleep_data.yearly_minutes);
-                        
-                        // Calculate total minutes since year start
-                        let year_minutes = get_minutes_since_year_start();
-                        let year_formatted = format_sleep_minutes(year_minutes);
-                        
-                        let details = format!(
-                            "Today: {} | Hours since start of year: {}",
-                            daily_formatted,
-                            year_formatted
-                        );
-                        let state = format!(
-                            "Monthly: {} | Yearly: {}",
-                            monthly_formatted,
-                            yearly_formatted
-                        );
-                        
-                        println!(
-                            "Fetched sleep - Today: {} ({}m), Monthly: {} ({}m), Yearly: {} ({}m)",
-                            daily_formatted, sleep_data.daily_minutes,
-                            monthly_formatted, sleep_data.monthly_minutes,
-                            yearly_formatted, sleep_data.yearly_minutes
-                        );
-                        
-                        if let Some(file_path) = obs_sleep_file {
-                            write_obs_sleep_file(&sleep_data, file_path);
-                        }
-
-                        if let Some(ref mut sleep_drpc) = sleep_drpc_opt {
-                            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                sleep_drpc.set_activity(|act| {
-                                    let mut activity = act.state(state)
-                                        .details(&details)
-                                        .timestamps(|timestamps| {
-                                            timestamps.start(start_timestamp).end(end_timestamp)
-                                        });
-                                    
-                                    if !sleep_large_image_key.is_empty() {
-                                        activity = activity.assets(|assets| {
-                                            assets.large_image(sleep_large_image_key)
-                                                .large_text("Getting rest!")
-                                        });
-                                    }
-                                    
-                                    activity
-                                })
-                            }));
-
-                            match result {
-                                Ok(Ok(_)) => {
-                                    println!("✅ Sleep activity set successfully");
-                                    // Clear other activities
-                                    if let Some(ref mut steps_drpc) = steps_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = steps_drpc.clear_activity();
-                                        }));
-                                    }
-                                    if let Some(ref mut water_drpc) = water_drpc_opt {
-                                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                            let _ = water_drpc.clear_activity();
-                                        }));
-                                    }
-                                    // Move to next activity (back to steps)
-                                    current_activity = get_next_activity(0, steps_enabled, water_enabled, sleep_enabled);
-                                }
-                                Ok(Err(e)) => {
-                                    eprintln!("Failed to set sleep activity: {}", e);
-                                    return Err(format!("Sleep Discord RPC connection lost: {}", e).into());
-                                }
-                                Err(_) => {
-                                    eprintln!("Panic caught while setting sleep activity. Reconnecting...");
-                                    return Err("Panic in set_activity".into());
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error fetching sleep: {}", e);
-                        if let Some(ref mut sleep_drpc) = sleep_drpc_opt {
-                            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                sleep_drpc.set_activity(|act| {
-                                    act.state("Unable to fetch sleep").details("API connection error")
-                                })
-                            }));
-                        }
-                        current_activity = get_next_activity(0, steps_enabled, water_enabled, sleep_enabled);
+                if let Some(ref history) = history {
+                    if let Err(e) = history.record(metric.as_ref()) {
+                        eprintln!("Failed to record {} history: {}", metric.id(), e);
                     }
                 }
-            }
-            _ => {
-                // Current activity is disabled or invalid, find next enabled one
-                current_activity = get_next_activity(current_activity, steps_enabled, water_enabled, sleep_enabled);
-                if !steps_enabled && !water_enabled && !sleep_enabled {
-                    // All disabled - just wait
-                    thread::sleep(Duration::from_secs(60));
-                    continue;
+                streak::update(metric.as_mut(), &api, history.as_ref(), &mut PastDays::default());
+                if let Err(e) = obs::write_metric(metric.as_ref()) {
+                    eprintln!("Failed to write OBS {} file: {}", metric.id(), e);
                 }
             }
+            Err(e) => eprintln!("Error fetching {}: {}", metric.id(), e),
         }
+    }
 
-        // Wait 60 seconds before next update
-        thread::sleep(Duration::from_secs(60));
+    let metric = metrics[slot].as_ref();
+    let activity = if fetched[slot] {
+        Activity::for_metric(metric)
+    } else {
+        Activity::stale(metric)
+    };
+    if dry_run {
+        dry_run_presence(config, &metrics).show(slot, &activity)?;
+        return Ok(());
     }
+
+    let mut presence = discord_presence(config, &metrics);
+    presence.show(slot, &activity)?;
+    println!("✅ {} activity set, holding for {} seconds", metrics[slot].label(), hold);
+    thread::sleep(Duration::from_secs(hold));
+    Ok(())
 }
 
-// Helper function to get next enabled activity (0=steps, 1=water, 2=sleep)
-fn get_next_activity(current: usize, steps_enabled: bool, water_enabled: bool, sleep_enabled: bool) -> usize {
-    let enabled = [steps_enabled, water_enabled, sleep_enabled];
-    
-    // Start from next position
-    for i in 1..=3 {
-        let idx = (current + i) % 3;
-        if enabled[idx] {
-            return idx;
+fn main() {
+    // Load environment variables from .env file
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+    VERBOSE.store(cli.verbose, Ordering::Relaxed);
+    if let Err(e) = dispatch(cli) {
+        eprintln!("Error: {}", e);
+        if let Some(Error::Api(ApiError::Unauthorized { .. })) = e.downcast_ref::<Error>() {
+            eprintln!("🔒 The API token was rejected: run `rpc login` to get a new one");
         }
+        std::process::exit(1);
     }
-    
-    // Fallback to first enabled
-    if steps_enabled { 0 }
-    else if water_enabled { 1 }
-    else if sleep_enabled { 2 }
-    else { 0 }
 }
 
-fn fetch_steps_summary(api_url: &str, token: &str) -> Result<StepsSummaryResponse, Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::new();
-    let url = format!("{}/api/steps/summary?token={}", api_url, token);
-
-    let response = client.get(&url).send()?;
-
-    let status = response.status();
-    if status.is_success() {
-        let summary: StepsSummaryResponse = response.json()?;
-        Ok(summary)
-    } else {
-        // Try to parse error response, fallback to status code
-        let error_msg = if let Ok(error_response) = response.json::<ErrorResponse>() {
-            error_response.error
-        } else {
-            format!("HTTP {} {}", status, status.canonical_reason().unwrap_or("Unknown"))
-        };
-        Err(error_msg.into())
+fn dispatch(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load(cli.config.as_deref())?;
+    verbose!("Configuration source: {:?}", config.source);
+    match cli.command {
+        None | Some(Command::Run) => Ok(run(&config, cli.dry_run)?),
+        Some(Command::Once { metric, hold }) => once(&config, metric.as_deref(), hold, cli.dry_run),
+        Some(Command::Status) => status(&config),
+        Some(Command::CheckConfig) => check_config(&config),
+        Some(Command::Export { output }) => export(&config, output.as_deref()),
+        Some(Command::History { metric, date, days }) => history(&config, metric.as_deref(), date.as_deref(), days),
+        Some(Command::Achievements) => achievements(&config),
+        Some(Command::Login) => login(&config),
+        Some(Command::Register) => register(&config),
+        Some(Command::Logout) => logout(&config),
+        Some(Command::Steps { action }) => submit_steps(&config, action, cli.dry_run),
     }
 }
 
-fn fetch_water_summary(api_url: &str, token: &str) -> Result<WaterSummaryResponse, Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::new();
-    let url = format!("{}/api/water/summary?token={}", api_url, token);
+fn run(config: &Config, dry_run: bool) -> Result<(), Error> {
+    // Check the whole configuration up front
+    config.validate()?;
+    let status = status_output(dry_run);
+    if let Some(ref path) = config.source {
+        status.print(format_args!("Loaded configuration from {}", path.display()));
+    }
 
-    let response = client.get(&url).send()?;
+    let api = api_client(config, true)?;
+    status.print(format_args!("Connecting to API: {} (auth: {:?})", api.base_url(), api.auth_mode()));
 
-    let status = response.status();
-    if status.is_success() {
-        let summary: WaterSummaryResponse = response.json()?;
-        Ok(summary)
-    } else {
-        // Try to parse error response, fallback to status code
-        let error_msg = if let Ok(error_response) = response.json::<ErrorResponse>() {
-            error_response.error
-        } else {
-            format!("HTTP {} {}", status, status.canonical_reason().unwrap_or("Unknown"))
-        };
-        Err(error_msg.into())
+    if let Some(client_id) = config.single_client_id() {
+        status.print(format_args!("Using a single Discord Client ID for all metrics: {}", client_id));
     }
-}
-
-fn fetch_sleep(api_url: &str, token: &str, date: &str) -> Result<SleepResponse, Box<dyn std::error::Error>> {
-    let client = reqwest::blocking::Client::new();
-    let url = format!("{}/api/sleep/summary?token={}&date={}", api_url, token, date);
 
-    let response = client.get(&url).send()?;
+    // Build the rotation from the enabled metrics, in display order
+    let mut metrics = config.enabled_metrics();
+    let cache = open_cache(config);
+    restore_cached(cache.as_ref(), &mut metrics);
+    let mut rotation = Rotation::new(metrics).with_status_output(status);
+    // Dry runs read the cache but leave it as it was
+    if let Some(cache) = cache.filter(|_| !dry_run) {
+        rotation = rotation.with_cache(cache);
+    }
+    // Dry runs must not add to the history that streaks are counted from
+    if !dry_run {
+        if let Some(history) = open_history(config) {
+            rotation = rotation.with_history(history);
+        }
+    }
+    // Milestones reached in a dry run would otherwise never be celebrated for real
+    if let Some(achievements) = open_achievements(config).filter(|_| !dry_run) {
+        rotation = rotation.with_achievements(achievements);
+    }
+    for metric in rotation.metrics() {
+        if config.single_client_id().is_none() {
+            status.print(format_args!("Using {} Discord Client ID: {}", metric.label(), metric.options().client_id));
+        }
+        if let Some(ref file) = metric.options().obs_file {
+            status.print(format_args!("OBS {} file: {}", metric.id(), file.display()));
+        }
+    }
+    if rotation.metrics().is_empty() {
+        status.print(format_args!("All metrics are disabled"));
+        // Nothing to show - just wait
+        loop {
+            thread::sleep(Duration::from_secs(60));
+        }
+    }
 
-    let status = response.status();
-    if status.is_success() {
-        let sleep: SleepResponse = response.json()?;
-        Ok(sleep)
-    } else {
-        // Try to parse error response, fallback to status code
-        let error_msg = if let Ok(error_response) = response.json::<ErrorResponse>() {
-            error_response.error
-        } else {
-            format!("HTTP {} {}", status, status.canonical_reason().unwrap_or("Unknown"))
-        };
-        Err(error_msg.into())
+    if dry_run {
+        status.print(format_args!("Dry run: rendering presence as JSON instead of connecting to Discord"));
+        let mut presence = dry_run_presence(config, rotation.metrics());
+        return rotation.run(&api, &mut presence);
     }
+
+    // Let `rpc steps` find us to request a refresh; a dry run must not
+    // take the signals meant for the real daemon
+    let _pid_file = control::PidFile::create()?;
+
+    // Rotate through the enabled metrics; each Discord client reconnects on its own
+    let mut presence = discord_presence(config, rotation.metrics());
+    rotation.run(&api, &mut presence)
 }
 
-fn format_number(n: i64) -> String {
-    if n >= 1_000_000 {
-        format!("{:.2}M", n as f64 / 1_000_000.0)
-    } else if n >= 1_000 {
-        format!("{:.2}K", n as f64 / 1_000.0)
+// Dry runs keep stdout for the JSON activities
+fn status_output(dry_run: bool) -> StatusOutput {
+    if dry_run {
+        StatusOutput::Stderr
     } else {
-        format!("{}", n)
+        StatusOutput::Stdout
     }
 }
-
-fn get_day_timestamps() -> (u64, u64) {
-    let now = Local::now();
-    let today = now.date_naive();
-    
-    // Start timestamp: today at 00:00:00 (midnight start of day)
-    let today_start = Local.from_local_datetime(&today.and_hms_opt(0, 0, 0).unwrap())
-        .single()
-        .expect("Failed to create today midnight");
-    let start_timestamp = today_start.timestamp() as u64;
-    
-    // End timestamp: today at 23:59:59 (end of day)
-    // Since this runs every 30 seconds, if we're past 23:59:59, 
-    // now.date_naive() will already be the next day, so this will be correct
-    let today_end = Local.from_local_datetime(&today.and_hms_opt(23, 59, 59).unwrap())
-        .single()
-        .expect("Failed to create today end");
-    let end_timestamp = today_end.timestamp() as u64;
-    
-    (start_timestamp, end_timestamp)
-}
-
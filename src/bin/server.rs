@@ -0,0 +1,59 @@
+use discord_rpc::server::Server;
+use discord_rpc::store::Store;
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+#[derive(Parser)]
+#[command(name = "server", version, about = "Self-hosted step tracker API backed by SQLite")]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, short, default_value = "127.0.0.1:8000")]
+    bind: String,
+    /// SQLite database file (defaults to server.db in the user data directory)
+    #[arg(long, short, value_name = "FILE")]
+    database: Option<PathBuf>,
+    /// Don't print a line per request
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+fn default_database() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("step-tracker-rpc").join("server.db"))
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let Some(database) = cli.database.or_else(default_database) else {
+        eprintln!("❌ No data directory for the current user, pass --database");
+        process::exit(1);
+    };
+    if let Some(parent) = database.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            eprintln!("❌ Could not create {}: {}", parent.display(), e);
+            process::exit(1);
+        }
+    }
+    let store = match Store::open(&database) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ Could not open {}: {}", database.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let server = match Server::start(&cli.bind, store) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("❌ Could not listen on {}: {}", cli.bind, e);
+            process::exit(1);
+        }
+    };
+    server.set_logging(!cli.quiet);
+
+    println!("🚀 Step tracker server listening on {}", cli.bind);
+    println!("   Database: {}", database.display());
+    server.wait();
+}
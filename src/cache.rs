@@ -0,0 +1,84 @@
+use crate::metrics::Metric;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The last successfully fetched data of a metric.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub fetched_at: DateTime<Local>,
+    pub data: serde_json::Value,
+}
+
+/// Default directory holding the last-known-good data, one file per metric.
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("step-tracker-rpc"))
+}
+
+/// The last-known-good data of every metric, stored in one directory.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// A cache in `dir`, which is created on the first store.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, metric_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", metric_id))
+    }
+
+    /// Save the metric's latest data, if it has any.
+    pub fn store(&self, metric: &dyn Metric) -> io::Result<()> {
+        let (Some(data), Some(fetched_at)) = (metric.snapshot(), metric.fetched_at()) else {
+            return Ok(());
+        };
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.entry_path(metric.id());
+        let json = serde_json::to_vec(&CacheEntry { fetched_at, data })?;
+        // Write then rename, so a crash never leaves a truncated entry behind
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, path)
+    }
+
+    /// The cached entry for a metric, if one was stored.
+    pub fn load(&self, metric_id: &str) -> io::Result<Option<CacheEntry>> {
+        match fs::read(self.entry_path(metric_id)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load the cached entry into `metric`, returning when it was fetched.
+    ///
+    /// Returns `Ok(None)` if nothing is cached for the metric.
+    pub fn restore(&self, metric: &mut dyn Metric) -> io::Result<Option<DateTime<Local>>> {
+        match self.load(metric.id())? {
+            Some(entry) => {
+                metric.restore(entry.data, entry.fetched_at)?;
+                Ok(Some(entry.fetched_at))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Forget all cached data, e.g. when logging out of the account it belongs to.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
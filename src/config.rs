@@ -0,0 +1,997 @@
+use crate::achievements::{self, Milestone, Period};
+use crate::api::AuthMode;
+use crate::cache;
+use crate::credentials;
+use crate::history;
+use crate::metrics::{Metric, MetricOptions, SleepMetric, StepsMetric, WaterMetric};
+use crate::presence::{Badge, Badges, Button, MAX_BUTTONS};
+use crate::retry::{CircuitBreaker, RetryPolicy};
+use crate::template::{Template, Templates};
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Config file looked up in the working directory when no path is given
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Errors produced while loading or validating the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, source: toml::de::Error },
+    /// Every problem found, so they can all be fixed in one go.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            ConfigError::Parse { path, source } => write!(f, "failed to parse {}: {}", path.display(), source),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub discord: DiscordConfig,
+    pub timing: TimingConfig,
+    pub retry: RetryConfig,
+    pub cache: CacheConfig,
+    pub history: HistoryConfig,
+    pub achievements: AchievementsConfig,
+    pub steps: MetricConfig,
+    pub water: MetricConfig,
+    pub sleep: MetricConfig,
+    /// File the configuration was read from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    // Invalid environment overrides, reported by validate()
+    #[serde(skip)]
+    env_problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub url: Option<String>,
+    /// Falls back to the token stored by `rpc login` when unset.
+    pub token: Option<String>,
+    pub auth: AuthMode,
+}
+
+/// How metrics are mapped onto Discord applications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscordMode {
+    /// One application per metric, each with its own `client_id` and name.
+    #[default]
+    Multi,
+    /// A single application whose presence rotates between metrics.
+    Single,
+}
+
+impl std::str::FromStr for DiscordMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "multi" => Ok(DiscordMode::Multi),
+            "single" => Ok(DiscordMode::Single),
+            other => Err(format!("unknown Discord mode '{}' (expected 'multi' or 'single')", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub mode: DiscordMode,
+    /// Application used in single mode.
+    pub client_id: Option<u64>,
+    /// Discord's IPC socket, when it isn't in one of the usual locations.
+    pub ipc_path: Option<PathBuf>,
+}
+
+/// Durations accept either a number of seconds or a string such as `"90s"`,
+/// `"2m"` or `"1h"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    /// How long each metric is shown unless it sets its own `display_duration`.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub refresh_interval: Duration,
+    /// Minimum time between two fetches of the same metric. Zero refetches
+    /// every time the metric comes up in the rotation.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub fetch_interval: Duration,
+    /// How long Discord is given to answer when connecting.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub connect_wait: Duration,
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(60),
+            fetch_interval: Duration::ZERO,
+            connect_wait: Duration::from_secs(2),
+        }
+    }
+}
+
+/// How failed API requests are retried and when to stop calling the API
+/// altogether for a while.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts per request, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub base_delay: Duration,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max_delay: Duration,
+    /// Consecutive failed requests after which the API is left alone.
+    pub failure_threshold: u32,
+    /// How long the API is left alone; doubled each time it is still down.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub cooldown: Duration,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max_cooldown: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        Self {
+            max_attempts: policy.max_attempts,
+            base_delay: policy.base_delay,
+            max_delay: policy.max_delay,
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(60),
+            max_cooldown: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            base_delay: self.base_delay,
+            max_delay: self.max_delay,
+        }
+    }
+
+    pub fn circuit_breaker(&self) -> CircuitBreaker {
+        CircuitBreaker::new(self.failure_threshold, self.cooldown, self.max_cooldown)
+    }
+}
+
+/// The last-known-good data shown while the API is unavailable.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Defaults to `step-tracker-rpc` in the user cache directory.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, path: None }
+    }
+}
+
+impl CacheConfig {
+    /// The cache directory, if caching is enabled and a location is known.
+    pub fn path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.path.clone().or_else(cache::default_dir)
+    }
+}
+
+/// The local log of fetched values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Defaults to `history.db` in the user data directory.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, path: None }
+    }
+}
+
+impl HistoryConfig {
+    /// The database file, if history is enabled and a location is known.
+    pub fn path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.path.clone().or_else(history::default_path)
+    }
+}
+
+/// Milestones and personal bests, and how unlocking one is shown.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AchievementsConfig {
+    pub enabled: bool,
+    /// Defaults to `achievements.json` in the user data directory.
+    pub path: Option<PathBuf>,
+    /// How long an unlocked achievement is shown.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub duration: Duration,
+    /// Shown with unlocked achievements; the metric's image when empty.
+    pub image_key: String,
+    /// Whether the built-in milestones are checked.
+    pub default_milestones: bool,
+    /// Milestones checked in addition to (or instead of) the built-in ones.
+    pub milestones: Vec<MilestoneConfig>,
+}
+
+impl Default for AchievementsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            duration: Duration::from_secs(15),
+            image_key: String::new(),
+            default_milestones: true,
+            milestones: Vec::new(),
+        }
+    }
+}
+
+/// A milestone as written in the configuration. `value` is read like a goal.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MilestoneConfig {
+    pub metric: String,
+    pub period: Period,
+    pub value: GoalValue,
+    pub title: Option<String>,
+}
+
+impl AchievementsConfig {
+    /// The file achievements are saved to, if they are enabled and a
+    /// location is known.
+    pub fn path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.path.clone().or_else(achievements::default_path)
+    }
+
+    /// Every milestone to check, or a description of the first invalid one.
+    pub fn milestones(&self) -> Result<Vec<Milestone>, String> {
+        let mut milestones = if self.default_milestones {
+            achievements::default_milestones()
+        } else {
+            Vec::new()
+        };
+        for (index, milestone) in self.milestones.iter().enumerate() {
+            let metric = milestone.metric.as_str();
+            if !matches!(metric, "steps" | "water" | "sleep") {
+                return Err(format!(
+                    "[achievements] milestone {}: unknown metric '{}' (expected steps, water or sleep)",
+                    index + 1,
+                    metric
+                ));
+            }
+            let threshold = parse_goal(metric, &milestone.value)
+                .map_err(|e| format!("[achievements] milestone {}: value {}", index + 1, e))?;
+            let title = match milestone.title {
+                Some(ref title) if !title.is_empty() => title.clone(),
+                _ => achievements::describe(metric, milestone.period, threshold),
+            };
+            milestones.push(Milestone::new(metric, milestone.period, threshold, title));
+        }
+        Ok(milestones)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricConfig {
+    pub enabled: bool,
+    pub client_id: Option<u64>,
+    pub image_key: String,
+    pub hover_text: Option<String>,
+    pub obs_file: Option<PathBuf>,
+    /// Overrides `[timing] refresh_interval` for this metric.
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub display_duration: Option<Duration>,
+    /// Overrides `[timing] fetch_interval` for this metric.
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub fetch_interval: Option<Duration>,
+    /// Daily goal, see [`parse_goal`].
+    pub goal: Option<GoalValue>,
+    pub goal_image_key: Option<String>,
+    pub goal_hover_text: Option<String>,
+    pub templates: TemplatesConfig,
+    pub buttons: Vec<ButtonConfig>,
+    pub badges: BadgesConfig,
+}
+
+/// A link shown under a metric's activity, see [`Button`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ButtonConfig {
+    pub label: String,
+    pub url: String,
+}
+
+/// Template texts replacing a metric's built-in ones, see [`Template`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplatesConfig {
+    pub details: Option<String>,
+    pub state: Option<String>,
+    pub hover_text: Option<String>,
+    pub obs: Option<String>,
+}
+
+impl TemplatesConfig {
+    fn fields(&self) -> [(&'static str, &Option<String>); 4] {
+        [
+            ("details", &self.details),
+            ("state", &self.state),
+            ("hover_text", &self.hover_text),
+            ("obs", &self.obs),
+        ]
+    }
+
+    // Invalid templates are reported by validate() and left unset here
+    fn templates(&self) -> Templates {
+        let parse = |source: &Option<String>| source.as_deref().and_then(|source| Template::parse(source).ok());
+        Templates {
+            details: parse(&self.details),
+            state: parse(&self.state),
+            hover_text: parse(&self.hover_text),
+            obs: parse(&self.obs),
+        }
+    }
+}
+
+/// Small images shown over a metric's large image, see [`Badges`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BadgesConfig {
+    pub stale: Option<BadgeConfig>,
+    pub goal_met: Option<BadgeConfig>,
+    pub streak: Option<BadgeConfig>,
+    pub default: Option<BadgeConfig>,
+}
+
+/// A badge's image key and optional hover text template.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BadgeConfig {
+    pub image_key: String,
+    pub text: Option<String>,
+}
+
+impl BadgesConfig {
+    fn fields(&self) -> [(&'static str, &Option<BadgeConfig>); 4] {
+        [
+            ("stale", &self.stale),
+            ("goal_met", &self.goal_met),
+            ("streak", &self.streak),
+            ("default", &self.default),
+        ]
+    }
+
+    fn fields_mut(&mut self) -> [(&'static str, &mut Option<BadgeConfig>); 4] {
+        [
+            ("STALE", &mut self.stale),
+            ("GOAL_MET", &mut self.goal_met),
+            ("STREAK", &mut self.streak),
+            ("DEFAULT", &mut self.default),
+        ]
+    }
+
+    // Badges without an image or with an invalid text are reported by
+    // validate() and left unset here
+    fn badges(&self) -> Badges {
+        let badge = |config: &Option<BadgeConfig>| {
+            let config = config.as_ref().filter(|config| !config.image_key.is_empty())?;
+            let text = match config.text.as_deref() {
+                Some(source) => Some(Template::parse(source).ok()?),
+                None => None,
+            };
+            Some(Badge {
+                image_key: config.image_key.clone(),
+                text,
+            })
+        };
+        Badges {
+            stale: badge(&self.stale),
+            goal_met: badge(&self.goal_met),
+            streak: badge(&self.streak),
+            default: badge(&self.default),
+        }
+    }
+}
+
+/// A daily goal as written in the configuration: a plain number in the
+/// metric's unit, or a string with a unit such as `"10K"`, `"2.5 L"` or `"8h"`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum GoalValue {
+    Number(f64),
+    Text(String),
+}
+
+impl Default for MetricConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            client_id: None,
+            image_key: String::new(),
+            hover_text: None,
+            obs_file: None,
+            display_duration: None,
+            fetch_interval: None,
+            goal: None,
+            goal_image_key: None,
+            goal_hover_text: None,
+            templates: TemplatesConfig::default(),
+            buttons: Vec::new(),
+            badges: BadgesConfig::default(),
+        }
+    }
+}
+
+impl MetricConfig {
+    fn options(&self, name: &str, default_hover_text: &str, timing: &TimingConfig) -> MetricOptions {
+        MetricOptions {
+            client_id: self.client_id.unwrap_or_default(),
+            image_key: self.image_key.clone(),
+            hover_text: self.hover_text.clone().unwrap_or_else(|| default_hover_text.to_string()),
+            obs_file: self.obs_file.clone(),
+            display_duration: self.display_duration.unwrap_or(timing.refresh_interval),
+            fetch_interval: self.fetch_interval.unwrap_or(timing.fetch_interval),
+            // Invalid goals are reported by validate()
+            goal: self.goal.as_ref().and_then(|goal| parse_goal(name, goal).ok()),
+            goal_image_key: self.goal_image_key.clone().filter(|key| !key.is_empty()),
+            goal_hover_text: self.goal_hover_text.clone(),
+            templates: self.templates.templates(),
+            // Invalid buttons are reported by validate() and left out here
+            buttons: self
+                .buttons
+                .iter()
+                .filter_map(|button| Button::new(&button.label, &button.url).ok())
+                .take(MAX_BUTTONS)
+                .collect(),
+            badges: self.badges.badges(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration file and apply environment variable overrides.
+    ///
+    /// Without an explicit `path`, `config.toml` in the working directory and
+    /// then in the user config directory are tried; if neither exists the
+    /// configuration comes from the environment alone.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => match default_config_path() {
+                Some(path) => Self::from_file(&path)?,
+                None => Config::default(),
+            },
+        };
+
+        config.apply_env();
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config: Config = toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.source = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    // Environment variables take precedence over the file
+    fn apply_env(&mut self) {
+        let problems = &mut self.env_problems;
+        if let Some(url) = env_var("API_URL") {
+            self.api.url = Some(url);
+        }
+        if let Some(token) = env_var("API_TOKEN") {
+            self.api.token = Some(token);
+        }
+        if let Some(auth) = env_var("API_AUTH_MODE") {
+            match auth.parse() {
+                Ok(auth) => self.api.auth = auth,
+                Err(e) => problems.push(format!("API_AUTH_MODE: {}", e)),
+            }
+        }
+
+        if let Some(mode) = env_var("DISCORD_MODE") {
+            match mode.parse() {
+                Ok(mode) => self.discord.mode = mode,
+                Err(e) => problems.push(format!("DISCORD_MODE: {}", e)),
+            }
+        }
+        if let Some(client_id) = env_var("DISCORD_CLIENT_ID") {
+            match client_id.parse() {
+                Ok(id) => self.discord.client_id = Some(id),
+                Err(_) => problems.push(format!("DISCORD_CLIENT_ID must be a valid u64, got '{}'", client_id)),
+            }
+        }
+
+        if let Some(ipc_path) = env_var("DISCORD_IPC_PATH") {
+            self.discord.ipc_path = Some(PathBuf::from(ipc_path));
+        }
+
+        if let Some(enabled) = env_var("CACHE_ENABLED") {
+            self.cache.enabled = enabled.to_lowercase() == "true";
+        }
+        if let Some(path) = env_var("CACHE_PATH") {
+            self.cache.path = Some(PathBuf::from(path));
+        }
+
+        if let Some(enabled) = env_var("HISTORY_ENABLED") {
+            self.history.enabled = enabled.to_lowercase() == "true";
+        }
+        if let Some(path) = env_var("HISTORY_PATH") {
+            self.history.path = Some(PathBuf::from(path));
+        }
+
+        if let Some(enabled) = env_var("ACHIEVEMENTS_ENABLED") {
+            self.achievements.enabled = enabled.to_lowercase() == "true";
+        }
+        if let Some(path) = env_var("ACHIEVEMENTS_PATH") {
+            self.achievements.path = Some(PathBuf::from(path));
+        }
+        if let Some(image_key) = env_var("ACHIEVEMENTS_IMAGE_KEY") {
+            self.achievements.image_key = image_key;
+        }
+
+        for (name, duration) in [
+            ("REFRESH_INTERVAL", &mut self.timing.refresh_interval),
+            ("FETCH_INTERVAL", &mut self.timing.fetch_interval),
+            ("CONNECT_WAIT", &mut self.timing.connect_wait),
+            ("ACHIEVEMENTS_DURATION", &mut self.achievements.duration),
+            ("RETRY_BASE_DELAY", &mut self.retry.base_delay),
+            ("RETRY_MAX_DELAY", &mut self.retry.max_delay),
+            ("CIRCUIT_COOLDOWN", &mut self.retry.cooldown),
+            ("CIRCUIT_MAX_COOLDOWN", &mut self.retry.max_cooldown),
+        ] {
+            if let Some(value) = env_var(name) {
+                match parse_duration(&value) {
+                    Ok(value) => *duration = value,
+                    Err(e) => problems.push(format!("{}: {}", name, e)),
+                }
+            }
+        }
+
+        for (name, count) in [
+            ("RETRY_MAX_ATTEMPTS", &mut self.retry.max_attempts),
+            ("CIRCUIT_FAILURE_THRESHOLD", &mut self.retry.failure_threshold),
+        ] {
+            if let Some(value) = env_var(name) {
+                match value.parse() {
+                    Ok(value) => *count = value,
+                    Err(_) => problems.push(format!("{} must be a whole number, got '{}'", name, value)),
+                }
+            }
+        }
+
+        for (prefix, metric) in [
+            ("STEPS", &mut self.steps),
+            ("WATER", &mut self.water),
+            ("SLEEP", &mut self.sleep),
+        ] {
+            if let Some(enabled) = env_var(&format!("ENABLE_{}", prefix)) {
+                metric.enabled = enabled.to_lowercase() == "true";
+            }
+            if let Some(client_id) = env_var(&format!("{}_DISCORD_CLIENT_ID", prefix)) {
+                match client_id.parse() {
+                    Ok(id) => metric.client_id = Some(id),
+                    Err(_) => problems.push(format!("{}_DISCORD_CLIENT_ID must be a valid u64, got '{}'", prefix, client_id)),
+                }
+            }
+            if let Some(image_key) = env_var(&format!("{}_DISCORD_LARGE_IMAGE_KEY", prefix)) {
+                metric.image_key = image_key;
+            }
+            if let Some(hover_text) = env_var(&format!("{}_HOVER_TEXT", prefix)) {
+                metric.hover_text = Some(hover_text);
+            }
+            if let Some(obs_file) = env_var(&format!("OBS_{}_FILE", prefix)) {
+                metric.obs_file = Some(PathBuf::from(obs_file));
+            }
+            if let Some(goal) = env_var(&format!("{}_GOAL", prefix)) {
+                metric.goal = Some(GoalValue::Text(goal));
+            }
+            if let Some(image_key) = env_var(&format!("{}_GOAL_IMAGE_KEY", prefix)) {
+                metric.goal_image_key = Some(image_key);
+            }
+            if let Some(hover_text) = env_var(&format!("{}_GOAL_HOVER_TEXT", prefix)) {
+                metric.goal_hover_text = Some(hover_text);
+            }
+            for (suffix, template) in [
+                ("DETAILS_TEMPLATE", &mut metric.templates.details),
+                ("STATE_TEMPLATE", &mut metric.templates.state),
+                ("HOVER_TEMPLATE", &mut metric.templates.hover_text),
+                ("OBS_TEMPLATE", &mut metric.templates.obs),
+            ] {
+                if let Some(value) = env_var(&format!("{}_{}", prefix, suffix)) {
+                    *template = Some(value);
+                }
+            }
+            for index in 0..MAX_BUTTONS {
+                let label = env_var(&format!("{}_BUTTON_{}_LABEL", prefix, index + 1));
+                let url = env_var(&format!("{}_BUTTON_{}_URL", prefix, index + 1));
+                if label.is_none() && url.is_none() {
+                    continue;
+                }
+                // Buttons are numbered from 1 without gaps, so an override can
+                // only replace an existing button or add the next one
+                if index > metric.buttons.len() {
+                    problems.push(format!(
+                        "{}_BUTTON_{}_*: set without {}_BUTTON_{}_LABEL and {}_BUTTON_{}_URL",
+                        prefix,
+                        index + 1,
+                        prefix,
+                        index,
+                        prefix,
+                        index
+                    ));
+                    continue;
+                }
+                if index == metric.buttons.len() {
+                    metric.buttons.push(ButtonConfig::default());
+                }
+                let button = &mut metric.buttons[index];
+                if let Some(label) = label {
+                    button.label = label;
+                }
+                if let Some(url) = url {
+                    button.url = url;
+                }
+            }
+            for (state, badge) in metric.badges.fields_mut() {
+                if let Some(image_key) = env_var(&format!("{}_{}_BADGE", prefix, state)) {
+                    badge.get_or_insert_with(BadgeConfig::default).image_key = image_key;
+                }
+                if let Some(text) = env_var(&format!("{}_{}_BADGE_TEXT", prefix, state)) {
+                    badge.get_or_insert_with(BadgeConfig::default).text = Some(text);
+                }
+            }
+            for (suffix, duration) in [
+                ("DISPLAY_DURATION", &mut metric.display_duration),
+                ("FETCH_INTERVAL", &mut metric.fetch_interval),
+            ] {
+                let name = format!("{}_{}", prefix, suffix);
+                if let Some(value) = env_var(&name) {
+                    match parse_duration(&value) {
+                        Ok(value) => *duration = Some(value),
+                        Err(e) => problems.push(format!("{}: {}", name, e)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check everything needed to run the daemon, reporting all problems at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = self.env_problems.clone();
+
+        if let Err(problem) = self.api_url() {
+            problems.push(problem);
+        }
+
+        let single = self.discord.mode == DiscordMode::Single;
+        if single {
+            match self.discord.client_id {
+                None => problems.push("[discord] client_id is required in single mode (or set DISCORD_CLIENT_ID)".to_string()),
+                Some(0) => problems.push("[discord] client_id must not be 0".to_string()),
+                Some(_) => {}
+            }
+        }
+
+        if self.timing.refresh_interval.is_zero() {
+            problems.push("[timing] refresh_interval must be greater than zero".to_string());
+        }
+
+        if self.retry.max_attempts == 0 {
+            problems.push("[retry] max_attempts must be at least 1".to_string());
+        }
+        if self.retry.failure_threshold == 0 {
+            problems.push("[retry] failure_threshold must be at least 1".to_string());
+        }
+
+        if let Some(ref path) = self.cache.path {
+            if self.cache.enabled && (path.as_os_str().is_empty() || path.is_file()) {
+                problems.push(format!("[cache] path '{}' is not a directory path", path.display()));
+            }
+        }
+
+        if let Some(ref path) = self.history.path {
+            if self.history.enabled && (path.as_os_str().is_empty() || path.is_dir()) {
+                problems.push(format!("[history] path '{}' is not a file path", path.display()));
+            }
+        }
+
+        if self.achievements.enabled {
+            if let Some(ref path) = self.achievements.path {
+                if path.as_os_str().is_empty() || path.is_dir() {
+                    problems.push(format!("[achievements] path '{}' is not a file path", path.display()));
+                }
+            }
+            if self.achievements.duration.is_zero() {
+                problems.push("[achievements] duration must be greater than zero".to_string());
+            }
+            if let Err(problem) = self.achievements.milestones() {
+                problems.push(problem);
+            }
+        }
+
+        for (name, metric) in self.metric_configs() {
+            if !metric.enabled {
+                continue;
+            }
+            if metric.display_duration == Some(Duration::ZERO) {
+                problems.push(format!("[{}] display_duration must be greater than zero", name));
+            }
+            // Per-metric applications are only used in multi mode
+            match metric.client_id {
+                _ if single => {}
+                None => problems.push(format!(
+                    "[{}] client_id is required while {} is enabled (or set {}_DISCORD_CLIENT_ID)",
+                    name,
+                    name,
+                    name.to_uppercase()
+                )),
+                Some(0) => problems.push(format!("[{}] client_id must not be 0", name)),
+                Some(_) => {}
+            }
+            if let Some(ref obs_file) = metric.obs_file {
+                if obs_file.as_os_str().is_empty() || obs_file.is_dir() {
+                    problems.push(format!("[{}] obs_file '{}' is not a file path", name, obs_file.display()));
+                }
+            }
+            if let Some(ref goal) = metric.goal {
+                if let Err(e) = parse_goal(name, goal) {
+                    problems.push(format!("[{}] goal: {}", name, e));
+                }
+            }
+            for (field, source) in metric.templates.fields() {
+                if let Some(Err(e)) = source.as_deref().map(Template::parse) {
+                    problems.push(format!("[{}.templates] {}: {}", name, field, e));
+                }
+            }
+            if metric.buttons.len() > MAX_BUTTONS {
+                problems.push(format!(
+                    "[{}] buttons: Discord shows at most {}, got {}",
+                    name,
+                    MAX_BUTTONS,
+                    metric.buttons.len()
+                ));
+            }
+            for (index, button) in metric.buttons.iter().enumerate() {
+                if let Err(e) = Button::new(&button.label, &button.url) {
+                    problems.push(format!("[{}] button {}: {}", name, index + 1, e));
+                }
+            }
+            for (state, badge) in metric.badges.fields() {
+                let Some(badge) = badge else { continue };
+                if badge.image_key.is_empty() {
+                    problems.push(format!("[{}.badges] {}: image_key is required", name, state));
+                }
+                if let Some(Err(e)) = badge.text.as_deref().map(Template::parse) {
+                    problems.push(format!("[{}.badges] {}: text: {}", name, state, e));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// The API base URL, or a description of why it is unusable.
+    pub fn api_url(&self) -> Result<&str, String> {
+        match self.api.url.as_deref() {
+            None | Some("") => Err("[api] url is required (or set API_URL)".to_string()),
+            Some(url) if !(url.starts_with("http://") || url.starts_with("https://")) => {
+                Err(format!("[api] url '{}' must start with http:// or https://", url))
+            }
+            Some(url) => Ok(url),
+        }
+    }
+
+    /// The configured token, falling back to the one stored by `rpc login`.
+    pub fn api_token(&self) -> io::Result<Option<String>> {
+        match self.api.token {
+            Some(ref token) if !token.is_empty() => Ok(Some(token.clone())),
+            _ => credentials::load_token(),
+        }
+    }
+
+    /// The single application's client ID when running in single mode.
+    pub fn single_client_id(&self) -> Option<u64> {
+        match self.discord.mode {
+            DiscordMode::Single => self.discord.client_id,
+            DiscordMode::Multi => None,
+        }
+    }
+
+    fn metric_configs(&self) -> [(&'static str, &MetricConfig); 3] {
+        [("steps", &self.steps), ("water", &self.water), ("sleep", &self.sleep)]
+    }
+
+    /// Build the enabled metrics in display order.
+    pub fn enabled_metrics(&self) -> Vec<Box<dyn Metric>> {
+        let mut metrics: Vec<Box<dyn Metric>> = Vec::new();
+        if self.steps.enabled {
+            metrics.push(Box::new(StepsMetric::new(self.steps.options("steps", "I'm walking here!", &self.timing))));
+        }
+        if self.water.enabled {
+            metrics.push(Box::new(WaterMetric::new(self.water.options("water", "Staying hydrated!", &self.timing))));
+        }
+        if self.sleep.enabled {
+            metrics.push(Box::new(SleepMetric::new(self.sleep.options("sleep", "Getting rest!", &self.timing))));
+        }
+        metrics
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let local = PathBuf::from(DEFAULT_CONFIG_FILE);
+    if local.is_file() {
+        return Some(local);
+    }
+    credentials::config_dir()
+        .map(|dir| dir.join(DEFAULT_CONFIG_FILE))
+        .filter(|path| path.is_file())
+}
+
+// Treat empty variables as unset, as dotenv templates often leave them blank
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Parse a duration given as seconds (`90`) or with a unit suffix (`90s`, `2m`, `1h`).
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration (expected e.g. 30, \"30s\", \"2m\" or \"1h\")", value))?;
+    let multiplier: u64 = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        other => return Err(format!("unknown duration unit '{}' in '{}' (expected s, m or h)", other, value)),
+    };
+    let seconds = number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{}' is too long a duration", value))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a daily goal into the unit of the metric called `name`:
+///
+/// - steps: a count, optionally with a `K` or `M` suffix (`10000`, `"10K"`)
+/// - water: millilitres, or a string in `ml` or `L` (`2500`, `"2.5 L"`)
+/// - sleep: minutes, or a string in hours and minutes (`480`, `"8h"`, `"7h 30m"`)
+pub fn parse_goal(name: &str, goal: &GoalValue) -> Result<i64, String> {
+    let value = match goal {
+        GoalValue::Number(number) => *number,
+        GoalValue::Text(text) => {
+            let text: String = text
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != ',' && *c != '_')
+                .collect::<String>()
+                .to_lowercase();
+            let invalid = || format!("'{}' is not a valid {} goal", text, name);
+            let number = |s: &str| s.parse::<f64>().map_err(|_| invalid());
+            match name {
+                "steps" => match text.strip_suffix('k') {
+                    Some(thousands) => number(thousands)? * 1_000.0,
+                    None => match text.strip_suffix('m') {
+                        Some(millions) => number(millions)? * 1_000_000.0,
+                        None => number(&text)?,
+                    },
+                },
+                "water" => match text.strip_suffix("ml") {
+                    Some(ml) => number(ml)?,
+                    None => match text.strip_suffix('l') {
+                        Some(litres) => number(litres)? * 1_000.0,
+                        None => number(&text)?,
+                    },
+                },
+                "sleep" => match text.split_once('h') {
+                    Some((hours, "")) => number(hours)? * 60.0,
+                    Some((hours, minutes)) => {
+                        let minutes = minutes.strip_suffix("min").or(minutes.strip_suffix('m')).unwrap_or(minutes);
+                        number(hours)? * 60.0 + number(minutes)?
+                    }
+                    None => {
+                        let minutes = text.strip_suffix("min").or(text.strip_suffix('m')).unwrap_or(&text);
+                        number(minutes)?
+                    }
+                },
+                _ => number(&text)?,
+            }
+        }
+    };
+    if !value.is_finite() || value < 1.0 {
+        return Err(format!("must be greater than zero, got {}", value));
+    }
+    Ok(value.round() as i64)
+}
+
+// Either a number of seconds or a string understood by parse_duration
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Seconds(u64),
+    Text(String),
+}
+
+impl RawDuration {
+    fn into_duration(self) -> Result<Duration, String> {
+        match self {
+            RawDuration::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+            RawDuration::Text(text) => parse_duration(&text),
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    RawDuration::deserialize(deserializer)?
+        .into_duration()
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    deserialize_duration(deserializer).map(Some)
+}
@@ -0,0 +1,110 @@
+use crate::credentials;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often a waiting daemon checks for a refresh request
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Directory for the pid file and refresh flag: $XDG_RUNTIME_DIR when
+// available, otherwise the app config directory
+fn runtime_dir() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .map(|dir| dir.join("step-tracker-rpc"))
+        .or_else(credentials::config_dir)
+}
+
+fn pid_path() -> Option<PathBuf> {
+    runtime_dir().map(|dir| dir.join("rpc.pid"))
+}
+
+fn refresh_path() -> Option<PathBuf> {
+    runtime_dir().map(|dir| dir.join("refresh"))
+}
+
+/// Marks the current process as the running daemon until dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create() -> io::Result<Self> {
+        let path = pid_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runtime directory for the current user"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, std::process::id().to_string())?;
+        // Ignore refresh requests made while no daemon was running
+        if let Some(refresh) = refresh_path() {
+            let _ = fs::remove_file(refresh);
+        }
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Pid of the running daemon, if there is one.
+pub fn running_pid() -> Option<u32> {
+    let pid: u32 = fs::read_to_string(pid_path()?).ok()?.trim().parse().ok()?;
+    if pid == std::process::id() || process_alive(pid) {
+        Some(pid)
+    } else {
+        None
+    }
+}
+
+// A stale pid file is left behind if the daemon was killed
+fn process_alive(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        proc.join(pid.to_string()).exists()
+    } else {
+        // Without procfs there is no cheap check, so trust the pid file
+        true
+    }
+}
+
+/// Ask the running daemon to refetch and redisplay immediately.
+///
+/// Returns `false` without doing anything if no daemon is running.
+pub fn request_refresh() -> io::Result<bool> {
+    if running_pid().is_none() {
+        return Ok(false);
+    }
+    let Some(path) = refresh_path() else {
+        return Ok(false);
+    };
+    fs::write(path, b"")?;
+    Ok(true)
+}
+
+/// Consume a pending refresh request.
+pub fn take_refresh_request() -> bool {
+    match refresh_path() {
+        Some(path) => fs::remove_file(path).is_ok(),
+        None => false,
+    }
+}
+
+/// Sleep for `duration`, returning early with `true` if a refresh is requested.
+pub fn wait_for_refresh(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if take_refresh_request() {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Directory name used under the platform config directory
+const APP_DIR: &str = "step-tracker-rpc";
+
+/// Directory holding the app's per-user configuration and credentials,
+/// e.g. `~/.config/step-tracker-rpc` on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Location of the stored API token.
+pub fn token_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("token"))
+}
+
+/// Read the stored token, returning `None` if nobody has logged in yet.
+pub fn load_token() -> io::Result<Option<String>> {
+    let Some(path) = token_path() else {
+        return Ok(None);
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(token) => {
+            let token = token.trim().to_string();
+            Ok(if token.is_empty() { None } else { Some(token) })
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Store the token so that only the current user can read it.
+pub fn save_token(token: &str) -> io::Result<PathBuf> {
+    let path = token_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory for the current user"))?;
+
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent)?;
+    }
+
+    let mut file = open_private_file(&path)?;
+    file.write_all(token.as_bytes())?;
+    file.sync_all()?;
+    Ok(path)
+}
+
+/// Remove the stored token. Succeeds if there was none.
+pub fn delete_token() -> io::Result<()> {
+    let Some(path) = token_path() else {
+        return Ok(());
+    };
+
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(path)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(path: &std::path::Path) -> io::Result<()> {
+    fs::create_dir_all(path)
+}
+
+#[cfg(unix)]
+fn open_private_file(path: &std::path::Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // mode() only applies when the file is created, so enforce it on overwrite too
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn open_private_file(path: &std::path::Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new().write(true).create(true).truncate(true).open(path)
+}
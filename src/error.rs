@@ -0,0 +1,64 @@
+use crate::api::ApiError;
+use crate::config::ConfigError;
+use crate::presence::DiscordError;
+use std::fmt;
+use std::io;
+
+/// Any failure of the daemon, grouped by what the caller can do about it.
+#[derive(Debug)]
+pub enum Error {
+    /// No API token is configured or stored.
+    MissingToken,
+    Api(ApiError),
+    Discord(DiscordError),
+    Config(ConfigError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingToken => write!(f, "no API token found: run `rpc login` or set API_TOKEN in .env"),
+            Error::Api(e) => write!(f, "API error: {}", e),
+            Error::Discord(e) => write!(f, "Discord error: {}", e),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::MissingToken => None,
+            Error::Api(e) => Some(e),
+            Error::Discord(e) => Some(e),
+            Error::Config(e) => Some(e),
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        Error::Api(e)
+    }
+}
+
+impl From<DiscordError> for Error {
+    fn from(e: DiscordError) -> Self {
+        Error::Discord(e)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::metrics::Metric;
+use crate::models::Totals;
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY,
+        metric TEXT NOT NULL,
+        date TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        daily INTEGER NOT NULL,
+        monthly INTEGER NOT NULL,
+        yearly INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_metric_date ON history (metric, date, id);
+";
+
+/// One fetched value of a metric.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub metric: String,
+    /// The day the totals describe.
+    pub date: NaiveDate,
+    pub fetched_at: DateTime<Local>,
+    pub totals: Totals,
+}
+
+/// Local SQLite log of every distinct value fetched for each metric.
+///
+/// A value is appended only when it differs from the last one recorded for
+/// the same metric and day, so the table grows with changes rather than
+/// with fetches. The last entry of a day holds that day's final totals.
+pub struct History {
+    conn: Connection,
+}
+
+/// Where the history is kept unless configured otherwise.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("step-tracker-rpc").join("history.db"))
+}
+
+impl History {
+    /// Open or create the database at `path`, creating parent directories.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        if let Some(parent) = path.parent() {
+            // A missing directory is reported by SQLite below
+            let _ = fs::create_dir_all(parent);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Append the metric's latest data, if it has any and it changed.
+    /// Returns whether an entry was added.
+    pub fn record(&self, metric: &dyn Metric) -> rusqlite::Result<bool> {
+        match (metric.totals(), metric.fetched_at()) {
+            (Some(totals), Some(fetched_at)) => self.append(metric.id(), fetched_at, totals),
+            _ => Ok(false),
+        }
+    }
+
+    /// Append totals fetched at `fetched_at` for that day, unless they equal
+    /// the last ones recorded for it. Returns whether an entry was added.
+    pub fn append(&self, metric_id: &str, fetched_at: DateTime<Local>, totals: Totals) -> rusqlite::Result<bool> {
+        let date = fetched_at.date_naive();
+        if self.on(metric_id, date)?.map(|entry| entry.totals) == Some(totals) {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO history (metric, date, fetched_at, daily, monthly, yearly)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                metric_id,
+                date.to_string(),
+                fetched_at.to_rfc3339(),
+                totals.daily,
+                totals.monthly,
+                totals.yearly
+            ],
+        )?;
+        Ok(true)
+    }
+
+    /// The last value recorded for a day.
+    pub fn on(&self, metric_id: &str, date: NaiveDate) -> rusqlite::Result<Option<HistoryEntry>> {
+        self.conn
+            .query_row(
+                "SELECT metric, date, fetched_at, daily, monthly, yearly FROM history
+                 WHERE metric = ?1 AND date = ?2 ORDER BY id DESC LIMIT 1",
+                params![metric_id, date.to_string()],
+                entry,
+            )
+            .optional()
+    }
+
+    /// The last value recorded for each day from `from` to `to` inclusive,
+    /// oldest first. Days without data are left out.
+    pub fn days(&self, metric_id: &str, from: NaiveDate, to: NaiveDate) -> rusqlite::Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT metric, date, fetched_at, daily, monthly, yearly FROM history
+             WHERE id IN (
+                 SELECT MAX(id) FROM history
+                 WHERE metric = ?1 AND date BETWEEN ?2 AND ?3
+                 GROUP BY date
+             )
+             ORDER BY date",
+        )?;
+        let entries = statement.query_map(params![metric_id, from.to_string(), to.to_string()], entry)?;
+        entries.collect()
+    }
+
+    /// Every value recorded for a day, oldest first.
+    pub fn entries(&self, metric_id: &str, date: NaiveDate) -> rusqlite::Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT metric, date, fetched_at, daily, monthly, yearly FROM history
+             WHERE metric = ?1 AND date = ?2 ORDER BY id",
+        )?;
+        let entries = statement.query_map(params![metric_id, date.to_string()], entry)?;
+        entries.collect()
+    }
+}
+
+fn entry(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        metric: row.get(0)?,
+        date: row.get(1)?,
+        fetched_at: row.get(2)?,
+        totals: Totals {
+            daily: row.get(3)?,
+            monthly: row.get(4)?,
+            yearly: row.get(5)?,
+        },
+    })
+}
@@ -0,0 +1,311 @@
+use crate::presence::Activity;
+use serde_json::{json, Value};
+#[cfg(unix)]
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Highest socket index Discord uses: discord-ipc-0 through discord-ipc-9
+const SOCKET_COUNT: usize = 10;
+
+// Frames larger than this are not something Discord would send
+const MAX_FRAME_LEN: u32 = 1024 * 1024;
+
+// How long a request may wait for Discord's answer
+#[cfg_attr(windows, allow(dead_code))]
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Frame types of the Discord IPC protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Handshake = 0,
+    Frame = 1,
+    Close = 2,
+    Ping = 3,
+    Pong = 4,
+}
+
+impl Opcode {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Opcode::Handshake),
+            1 => Some(Opcode::Frame),
+            2 => Some(Opcode::Close),
+            3 => Some(Opcode::Ping),
+            4 => Some(Opcode::Pong),
+            _ => None,
+        }
+    }
+}
+
+/// Errors talking to the Discord client over IPC.
+#[derive(Debug)]
+pub enum IpcError {
+    /// No Discord IPC socket accepted a connection.
+    NotRunning,
+    /// Reading or writing the socket failed, e.g. because Discord quit.
+    Io(io::Error),
+    /// Discord closed the connection, e.g. for an unknown client ID.
+    Closed { code: i64, message: String },
+    /// Discord answered a request with an error.
+    Rejected { code: i64, message: String },
+    /// A frame that doesn't follow the protocol.
+    Protocol(String),
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpcError::NotRunning => write!(f, "Discord is not running (no IPC socket found)"),
+            IpcError::Io(e) => write!(f, "IPC connection failed: {}", e),
+            IpcError::Closed { code, message } => write!(f, "Discord closed the connection: {} ({})", message, code),
+            IpcError::Rejected { code, message } => write!(f, "Discord rejected the request: {} ({})", message, code),
+            IpcError::Protocol(message) => write!(f, "unexpected IPC message: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for IpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IpcError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IpcError {
+    fn from(e: io::Error) -> Self {
+        IpcError::Io(e)
+    }
+}
+
+/// Connection state of an [`IpcClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Not connected, either never or since a failure.
+    Disconnected,
+    /// Handshake completed; activities can be set.
+    Connected,
+}
+
+/// Write one frame: opcode and payload length as little-endian `u32`s,
+/// followed by the JSON payload.
+pub fn write_frame<W: Write>(out: &mut W, opcode: Opcode, payload: &Value) -> io::Result<()> {
+    let body = serde_json::to_vec(payload)?;
+    let mut frame = Vec::with_capacity(8 + body.len());
+    frame.extend_from_slice(&(opcode as u32).to_le_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&body);
+    out.write_all(&frame)?;
+    out.flush()
+}
+
+/// Read one frame written by [`write_frame`].
+pub fn read_frame<R: Read>(input: &mut R) -> Result<(Opcode, Value), IpcError> {
+    let mut header = [0u8; 8];
+    input.read_exact(&mut header)?;
+    let opcode = u32::from_le_bytes(header[..4].try_into().unwrap());
+    let len = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+    let opcode = Opcode::from_u32(opcode).ok_or_else(|| IpcError::Protocol(format!("unknown opcode {}", opcode)))?;
+    if len > MAX_FRAME_LEN {
+        return Err(IpcError::Protocol(format!("frame of {} bytes is too large", len)));
+    }
+
+    let mut body = vec![0u8; len as usize];
+    input.read_exact(&mut body)?;
+    let payload = serde_json::from_slice(&body).map_err(|e| IpcError::Protocol(format!("invalid JSON payload: {}", e)))?;
+    Ok((opcode, payload))
+}
+
+/// Every path Discord may be listening on, in the order they are tried.
+///
+/// Each temporary directory is searched directly and in the Flatpak and
+/// Snap sandboxes, for all ten socket indices.
+#[cfg(unix)]
+pub fn socket_candidates() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .filter_map(env::var_os)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    dirs.push(PathBuf::from("/tmp"));
+    dirs.dedup();
+
+    let mut candidates = Vec::new();
+    for dir in dirs {
+        for sandbox in ["", "app/com.discordapp.Discord", "snap.discord"] {
+            for index in 0..SOCKET_COUNT {
+                candidates.push(dir.join(sandbox).join(format!("discord-ipc-{}", index)));
+            }
+        }
+    }
+    candidates
+}
+
+/// The named pipes Discord may be listening on, in the order they are tried.
+#[cfg(windows)]
+pub fn socket_candidates() -> Vec<PathBuf> {
+    (0..SOCKET_COUNT)
+        .map(|index| PathBuf::from(format!(r"\\?\pipe\discord-ipc-{}", index)))
+        .collect()
+}
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+
+#[cfg(windows)]
+type Stream = std::fs::File;
+
+#[cfg(unix)]
+fn open_socket(candidates: Vec<PathBuf>, timeout: Duration) -> Result<Stream, IpcError> {
+    for path in candidates {
+        if let Ok(stream) = Stream::connect(&path) {
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            return Ok(stream);
+        }
+    }
+    Err(IpcError::NotRunning)
+}
+
+// Named pipes have no timeouts, so `timeout` is not enforced on Windows
+#[cfg(windows)]
+fn open_socket(candidates: Vec<PathBuf>, _timeout: Duration) -> Result<Stream, IpcError> {
+    for path in candidates {
+        if let Ok(pipe) = std::fs::OpenOptions::new().read(true).write(true).open(path) {
+            return Ok(pipe);
+        }
+    }
+    Err(IpcError::NotRunning)
+}
+
+/// A connection to the local Discord client for one application.
+///
+/// Every call is synchronous and bounded by a timeout. Any failure leaves
+/// the client [`ConnectionState::Disconnected`]; call
+/// [`IpcClient::connect`] again to reconnect.
+pub struct IpcClient {
+    client_id: u64,
+    socket_path: Option<PathBuf>,
+    stream: Option<Stream>,
+    nonce: u64,
+}
+
+impl IpcClient {
+    pub fn new(client_id: u64) -> Self {
+        Self {
+            client_id,
+            socket_path: None,
+            stream: None,
+            nonce: 0,
+        }
+    }
+
+    /// Connect to `path` only instead of searching the usual locations.
+    pub fn with_socket_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.socket_path = Some(path.into());
+        self
+    }
+
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        match self.stream {
+            Some(_) => ConnectionState::Connected,
+            None => ConnectionState::Disconnected,
+        }
+    }
+
+    /// Open the first available socket and complete the handshake, giving
+    /// Discord `timeout` to answer.
+    pub fn connect(&mut self, timeout: Duration) -> Result<(), IpcError> {
+        self.stream = None;
+        let candidates = match self.socket_path {
+            Some(ref path) => vec![path.clone()],
+            None => socket_candidates(),
+        };
+        let mut stream = open_socket(candidates, timeout)?;
+
+        let handshake = json!({ "v": 1, "client_id": self.client_id.to_string() });
+        write_frame(&mut stream, Opcode::Handshake, &handshake)?;
+        loop {
+            match read_frame(&mut stream)? {
+                (Opcode::Frame, payload) if payload["evt"] == "READY" => break,
+                (Opcode::Close, payload) => return Err(closed(&payload)),
+                (Opcode::Ping, payload) => write_frame(&mut stream, Opcode::Pong, &payload)?,
+                (opcode, _) => return Err(IpcError::Protocol(format!("expected READY, got {:?}", opcode))),
+            }
+        }
+
+        #[cfg(unix)]
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    pub fn set_activity(&mut self, activity: &Activity) -> Result<(), IpcError> {
+        let activity = serde_json::to_value(activity).map_err(|e| IpcError::Protocol(e.to_string()))?;
+        self.set_activity_value(activity)
+    }
+
+    pub fn clear_activity(&mut self) -> Result<(), IpcError> {
+        self.set_activity_value(Value::Null)
+    }
+
+    fn set_activity_value(&mut self, activity: Value) -> Result<(), IpcError> {
+        let args = json!({ "pid": std::process::id(), "activity": activity });
+        self.request("SET_ACTIVITY", args).map(drop)
+    }
+
+    // Send a command and wait for the response carrying the same nonce
+    fn request(&mut self, command: &str, args: Value) -> Result<Value, IpcError> {
+        let result = self.try_request(command, args);
+        if matches!(result, Err(IpcError::Io(_) | IpcError::Closed { .. } | IpcError::Protocol(_))) {
+            self.stream = None;
+        }
+        result
+    }
+
+    fn try_request(&mut self, command: &str, args: Value) -> Result<Value, IpcError> {
+        self.nonce += 1;
+        let nonce = self.nonce.to_string();
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| IpcError::Io(io::Error::new(io::ErrorKind::NotConnected, "not connected")))?;
+
+        write_frame(stream, Opcode::Frame, &json!({ "cmd": command, "args": args, "nonce": nonce }))?;
+        loop {
+            match read_frame(stream)? {
+                (Opcode::Frame, payload) if payload["nonce"] == nonce.as_str() => {
+                    if payload["evt"] == "ERROR" {
+                        return Err(IpcError::Rejected {
+                            code: payload["data"]["code"].as_i64().unwrap_or_default(),
+                            message: payload["data"]["message"].as_str().unwrap_or("unknown error").to_string(),
+                        });
+                    }
+                    return Ok(payload);
+                }
+                // Events and answers to earlier, timed out requests
+                (Opcode::Frame, _) | (Opcode::Pong, _) => {}
+                (Opcode::Ping, payload) => write_frame(stream, Opcode::Pong, &payload)?,
+                (Opcode::Close, payload) => return Err(closed(&payload)),
+                (Opcode::Handshake, _) => return Err(IpcError::Protocol("unexpected handshake".to_string())),
+            }
+        }
+    }
+}
+
+fn closed(payload: &Value) -> IpcError {
+    IpcError::Closed {
+        code: payload["code"].as_i64().unwrap_or_default(),
+        message: payload["message"].as_str().unwrap_or("no reason given").to_string(),
+    }
+}
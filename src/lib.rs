@@ -1,4 +1,29 @@
+pub mod achievements;
+pub mod api;
+pub mod cache;
+pub mod config;
+pub mod control;
+pub mod credentials;
+pub mod error;
+pub mod history;
+pub mod ipc;
+pub mod metrics;
+pub mod mock;
 pub mod models;
+pub mod obs;
+pub mod presence;
+pub mod retry;
+pub mod rotation;
+pub mod server;
+pub mod store;
+pub mod streak;
+pub mod template;
 
+pub use achievements::Achievements;
+pub use api::{ApiClient, ApiError, AuthMode};
+pub use config::{Config, ConfigError, DiscordMode};
+pub use error::Error;
+pub use history::History;
+pub use metrics::Metric;
 pub use models::*;
-
+pub use streak::Streak;
@@ -0,0 +1,642 @@
+use crate::api::{ApiClient, ApiError};
+use crate::models::*;
+use crate::presence::{Badge, Badges, Button};
+use crate::streak::Streak;
+use crate::template::{Template, Templates, Unit, Value, Variables};
+use chrono::{DateTime, Datelike, Local, TimeZone};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Shown in place of values before the first successful fetch
+const NO_DATA: &str = "No data yet";
+
+/// Per-metric display settings.
+#[derive(Debug, Clone)]
+pub struct MetricOptions {
+    pub client_id: u64,
+    pub image_key: String,
+    pub hover_text: String,
+    pub obs_file: Option<PathBuf>,
+    /// How long the metric stays on screen once shown.
+    pub display_duration: Duration,
+    /// Minimum time between two fetches; older data is reused until then.
+    pub fetch_interval: Duration,
+    /// Daily target in the metric's unit (steps, millilitres or minutes).
+    pub goal: Option<i64>,
+    /// Replaces `image_key` once the day's goal is met.
+    pub goal_image_key: Option<String>,
+    /// Replaces `hover_text` once the day's goal is met.
+    pub goal_hover_text: Option<String>,
+    /// Replace the built-in details, state, hover text and OBS text.
+    pub templates: Templates,
+    /// Links shown under the activity, at most [`MAX_BUTTONS`](crate::presence::MAX_BUTTONS).
+    pub buttons: Vec<Button>,
+    /// Small images shown over the large one, depending on the state.
+    pub badges: Badges,
+}
+
+impl Default for MetricOptions {
+    // Matches the [timing] defaults
+    fn default() -> Self {
+        Self {
+            client_id: 0,
+            image_key: String::new(),
+            hover_text: String::new(),
+            obs_file: None,
+            display_duration: Duration::from_secs(60),
+            fetch_interval: Duration::ZERO,
+            goal: None,
+            goal_image_key: None,
+            goal_hover_text: None,
+            templates: Templates::default(),
+            buttons: Vec::new(),
+            badges: Badges::default(),
+        }
+    }
+}
+
+/// How far the day's total is towards the daily goal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub value: i64,
+    pub goal: i64,
+}
+
+impl Progress {
+    /// Percentage of the goal reached, which may exceed 100.
+    pub fn percent(&self) -> i64 {
+        self.value.saturating_mul(100) / self.goal.max(1)
+    }
+
+    pub fn remaining(&self) -> i64 {
+        (self.goal - self.value).max(0)
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.value >= self.goal
+    }
+}
+
+/// A stat that can be fetched from the API and shown as Rich Presence.
+///
+/// `fetch` stores the latest value inside the metric; the formatting methods
+/// always describe the most recent successful fetch.
+pub trait Metric {
+    /// Lowercase identifier, e.g. `steps`.
+    fn id(&self) -> &str;
+
+    /// Human readable name, e.g. `Steps`.
+    fn label(&self) -> &str;
+
+    fn options(&self) -> &MetricOptions;
+
+    fn fetch(&mut self, api: &ApiClient) -> Result<(), ApiError>;
+
+    /// First line of the presence when no `details` template is set.
+    fn built_in_details(&self) -> String;
+
+    /// Second line of the presence when no `state` template is set.
+    fn built_in_state(&self) -> String;
+
+    /// Contents of the OBS text file when no `obs` template is set.
+    fn built_in_obs_text(&self) -> String;
+
+    /// Day, month and year totals of the latest data, in the metric's own
+    /// unit (steps, millilitres or minutes).
+    fn totals(&self) -> Option<Totals>;
+
+
+    /// The latest fetched data as JSON, if any.
+    fn snapshot(&self) -> Option<serde_json::Value>;
+
+    /// When the latest data was fetched, if it ever was.
+    fn fetched_at(&self) -> Option<DateTime<Local>>;
+
+    /// Replace the latest data with a snapshot taken at `fetched_at`.
+    fn restore(&mut self, snapshot: serde_json::Value, fetched_at: DateTime<Local>) -> Result<(), serde_json::Error>;
+
+    /// Days in a row the goal was met, once worked out from the history.
+    fn streak(&self) -> Option<Streak>;
+
+    fn set_streak(&mut self, streak: Option<Streak>);
+
+    /// What the totals measure.
+    fn unit(&self) -> Unit;
+
+    /// Format a value in the metric's unit, e.g. a daily total.
+    fn format_value(&self, value: i64) -> String {
+        self.unit().format_with_unit(value)
+    }
+
+    /// The values templates can use, empty before the first fetch.
+    fn variables(&self) -> Variables {
+        let mut variables = Variables::new();
+        let Some(totals) = self.totals() else {
+            return variables;
+        };
+        let unit = self.unit();
+        variables.set("daily", Value::Amount(totals.daily, unit));
+        variables.set("monthly", Value::Amount(totals.monthly, unit));
+        variables.set("yearly", Value::Amount(totals.yearly, unit));
+        if let Some(progress) = self.progress() {
+            variables.set("goal", Value::Amount(progress.goal, unit));
+            variables.set("goal_pct", Value::Number(progress.percent()));
+            variables.set("remaining", Value::Amount(progress.remaining(), unit));
+        }
+        if let Some(streak) = self.streak() {
+            variables.set("streak", Value::Number(i64::from(streak.current)));
+            variables.set("best_streak", Value::Number(i64::from(streak.longest)));
+        }
+        variables.set("label", Value::Text(self.label().to_string()));
+        variables.set("metric", Value::Text(self.id().to_string()));
+        if let Some(fetched_at) = self.fetched_at() {
+            variables.set("as_of", Value::Text(fetched_at.format("%H:%M").to_string()));
+        }
+        variables.set("year_elapsed", Value::Amount(minutes_since_year_start(), Unit::Minutes));
+        variables
+    }
+
+    /// Render one of the metric's templates, if it is set and there is data.
+    fn render(&self, template: Option<&Template>) -> Option<String> {
+        let template = template?;
+        self.totals()?;
+        Some(template.render(&self.variables()))
+    }
+
+    /// First line of the presence.
+    fn details(&self) -> String {
+        self.render(self.options().templates.details.as_ref())
+            .unwrap_or_else(|| self.built_in_details())
+    }
+
+    /// Second line of the presence.
+    fn state(&self) -> String {
+        self.render(self.options().templates.state.as_ref())
+            .unwrap_or_else(|| self.built_in_state())
+    }
+
+    /// Contents of the OBS text file.
+    fn obs_text(&self) -> String {
+        self.render(self.options().templates.obs.as_ref())
+            .unwrap_or_else(|| self.built_in_obs_text())
+    }
+
+    /// Progress of the latest daily total, if a goal is set.
+    fn progress(&self) -> Option<Progress> {
+        let goal = self.options().goal.filter(|goal| *goal > 0)?;
+        let totals = self.totals()?;
+        Some(Progress { value: totals.daily, goal })
+    }
+
+    fn goal_met(&self) -> bool {
+        self.progress().is_some_and(|progress| progress.is_met())
+    }
+
+    fn image_key(&self) -> &str {
+        match self.options().goal_image_key {
+            Some(ref key) if self.goal_met() => key,
+            _ => &self.options().image_key,
+        }
+    }
+
+    /// The badge for the current state, if one is set. `stale` is whether the
+    /// last values are shown because the API is unavailable.
+    fn badge(&self, stale: bool) -> Option<&Badge> {
+        let badges = &self.options().badges;
+        let streak = self.streak().is_some_and(|streak| streak.current > 0);
+        [
+            (stale, &badges.stale),
+            (self.goal_met(), &badges.goal_met),
+            (streak, &badges.streak),
+            (true, &badges.default),
+        ]
+        .into_iter()
+        .find_map(|(applies, badge)| badge.as_ref().filter(|_| applies))
+    }
+
+    /// The goal's hover text once it is met, otherwise the `hover_text`
+    /// template or plain text.
+    fn hover_text(&self) -> String {
+        match self.options().goal_hover_text {
+            Some(ref text) if self.goal_met() => text.clone(),
+            _ => self
+                .render(self.options().templates.hover_text.as_ref())
+                .unwrap_or_else(|| self.options().hover_text.clone()),
+        }
+    }
+}
+
+// "Today: 8.42K / 10K — 84%" with a goal, "Today: 8.42K" without
+fn today_line(today: &str, progress: Option<Progress>, format: impl Fn(i64) -> String) -> String {
+    match progress {
+        Some(progress) => format!(
+            "Today: {} / {} — {}%",
+            format(progress.value),
+            format(progress.goal),
+            progress.percent()
+        ),
+        None => format!("Today: {}", today),
+    }
+}
+
+// Put the remaining amount, or that the goal is met, in front of `state`
+fn with_remaining(state: String, progress: Option<Progress>, format: impl Fn(i64) -> String) -> String {
+    match progress {
+        Some(progress) if progress.is_met() => format!("Goal met ✅ | {}", state),
+        Some(progress) => format!("{} to go | {}", format(progress.remaining()), state),
+        None => state,
+    }
+}
+
+// Put the current streak after `state`, if there is one
+fn with_streak(state: String, streak: Option<Streak>) -> String {
+    match streak {
+        Some(streak) if streak.current > 0 => format!("{} | {}", state, streak),
+        _ => state,
+    }
+}
+
+// OBS line describing the streak, empty until it is known
+fn streak_line(streak: Option<Streak>) -> String {
+    match streak {
+        Some(streak) => format!("\nStreak: {} days (best: {})", streak.current, streak.longest),
+        None => String::new(),
+    }
+}
+
+// OBS lines describing the goal, empty without one
+fn goal_lines(progress: Option<Progress>, format: impl Fn(i64) -> String) -> String {
+    match progress {
+        Some(progress) if progress.is_met() => {
+            format!("\nGoal: {} ({}%)\nGoal met!", format(progress.goal), progress.percent())
+        }
+        Some(progress) => format!(
+            "\nGoal: {} ({}%)\nRemaining: {}",
+            format(progress.goal),
+            progress.percent(),
+            format(progress.remaining())
+        ),
+        None => String::new(),
+    }
+}
+
+/// What every metric keeps between fetches: its options, the latest data
+/// and what has been worked out from it.
+struct MetricState<T> {
+    options: MetricOptions,
+    latest: Option<T>,
+    fetched_at: Option<DateTime<Local>>,
+    streak: Option<Streak>,
+}
+
+impl<T: Serialize + DeserializeOwned> MetricState<T> {
+    fn new(options: MetricOptions) -> Self {
+        Self {
+            options,
+            latest: None,
+            fetched_at: None,
+            streak: None,
+        }
+    }
+
+    // Keep freshly fetched data
+    fn update(&mut self, latest: T) {
+        self.latest = Some(latest);
+        self.fetched_at = Some(Local::now());
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        self.latest.as_ref().and_then(|latest| serde_json::to_value(latest).ok())
+    }
+
+    fn restore(&mut self, snapshot: serde_json::Value, fetched_at: DateTime<Local>) -> Result<(), serde_json::Error> {
+        self.latest = Some(serde_json::from_value(snapshot)?);
+        self.fetched_at = Some(fetched_at);
+        Ok(())
+    }
+}
+
+// The Metric methods that only read or write the `data: MetricState`
+macro_rules! shared_metric_methods {
+    () => {
+        fn options(&self) -> &MetricOptions {
+            &self.data.options
+        }
+
+        fn snapshot(&self) -> Option<serde_json::Value> {
+            self.data.snapshot()
+        }
+
+        fn fetched_at(&self) -> Option<DateTime<Local>> {
+            self.data.fetched_at
+        }
+
+        fn streak(&self) -> Option<Streak> {
+            self.data.streak
+        }
+
+        fn set_streak(&mut self, streak: Option<Streak>) {
+            self.data.streak = streak;
+        }
+
+        fn restore(&mut self, snapshot: serde_json::Value, fetched_at: DateTime<Local>) -> Result<(), serde_json::Error> {
+            self.data.restore(snapshot, fetched_at)
+        }
+    };
+}
+
+pub struct StepsMetric {
+    data: MetricState<StepsSummaryResponse>,
+}
+
+impl StepsMetric {
+    pub fn new(options: MetricOptions) -> Self {
+        Self {
+            data: MetricState::new(options),
+        }
+    }
+}
+
+impl Metric for StepsMetric {
+    fn id(&self) -> &str {
+        "steps"
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Count("steps")
+    }
+
+    fn label(&self) -> &str {
+        "Steps"
+    }
+
+    fn fetch(&mut self, api: &ApiClient) -> Result<(), ApiError> {
+        self.data.update(api.steps_summary()?);
+        Ok(())
+    }
+
+    fn built_in_details(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => today_line(&format_number(summary.daily), self.progress(), format_short),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn built_in_state(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => {
+                let state = format!(
+                    "Monthly: {} | Yearly: {}",
+                    format_number(summary.monthly),
+                    format_number(summary.yearly)
+                );
+                with_streak(with_remaining(state, self.progress(), format_short), self.data.streak)
+            }
+            None => String::new(),
+        }
+    }
+
+    fn built_in_obs_text(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => format!(
+                "Today: {}\nMonthly: {}\nYearly: {}{}{}",
+                format_number(summary.daily),
+                format_number(summary.monthly),
+                format_number(summary.yearly),
+                goal_lines(self.progress(), format_short),
+                streak_line(self.data.streak)
+            ),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn totals(&self) -> Option<Totals> {
+        self.data.latest.as_ref().map(|summary| Totals {
+            daily: summary.daily,
+            monthly: summary.monthly,
+            yearly: summary.yearly,
+        })
+    }
+
+    shared_metric_methods!();
+}
+
+pub struct WaterMetric {
+    data: MetricState<WaterSummaryResponse>,
+}
+
+impl WaterMetric {
+    pub fn new(options: MetricOptions) -> Self {
+        Self {
+            data: MetricState::new(options),
+        }
+    }
+}
+
+impl Metric for WaterMetric {
+    fn id(&self) -> &str {
+        "water"
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Millilitres
+    }
+
+    fn label(&self) -> &str {
+        "Water"
+    }
+
+    fn fetch(&mut self, api: &ApiClient) -> Result<(), ApiError> {
+        self.data.update(api.water_summary()?);
+        Ok(())
+    }
+
+    fn built_in_details(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => today_line(&summary.daily_display, self.progress(), format_volume),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn built_in_state(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => {
+                let state = format!(
+                    "Monthly: {} | Yearly: {}",
+                    summary.monthly_display, summary.yearly_display
+                );
+                with_streak(with_remaining(state, self.progress(), format_volume), self.data.streak)
+            }
+            None => String::new(),
+        }
+    }
+
+    fn built_in_obs_text(&self) -> String {
+        match &self.data.latest {
+            Some(summary) => format!(
+                "Today: {}\nMonthly: {}\nYearly: {}{}{}",
+                summary.daily_display,
+                summary.monthly_display,
+                summary.yearly_display,
+                goal_lines(self.progress(), format_volume),
+                streak_line(self.data.streak)
+            ),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn totals(&self) -> Option<Totals> {
+        self.data.latest.as_ref().map(|summary| Totals {
+            daily: summary.daily_ml,
+            monthly: summary.monthly_ml,
+            yearly: summary.yearly_ml,
+        })
+    }
+
+    shared_metric_methods!();
+}
+
+pub struct SleepMetric {
+    data: MetricState<SleepResponse>,
+}
+
+impl SleepMetric {
+    pub fn new(options: MetricOptions) -> Self {
+        Self {
+            data: MetricState::new(options),
+        }
+    }
+}
+
+impl Metric for SleepMetric {
+    fn id(&self) -> &str {
+        "sleep"
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Minutes
+    }
+
+    fn label(&self) -> &str {
+        "Sleep"
+    }
+
+    fn fetch(&mut self, api: &ApiClient) -> Result<(), ApiError> {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        self.data.update(api.sleep_summary(&today)?);
+        Ok(())
+    }
+
+    fn built_in_details(&self) -> String {
+        match &self.data.latest {
+            Some(sleep) => format!(
+                "{} | Hours since start of year: {}",
+                today_line(&format_sleep_minutes(sleep.daily_minutes), self.progress(), format_sleep_minutes),
+                format_sleep_minutes(minutes_since_year_start())
+            ),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn built_in_state(&self) -> String {
+        match &self.data.latest {
+            Some(sleep) => {
+                let state = format!(
+                    "Monthly: {} | Yearly: {}",
+                    format_sleep_minutes(sleep.monthly_minutes),
+                    format_sleep_minutes(sleep.yearly_minutes)
+                );
+                with_streak(with_remaining(state, self.progress(), format_sleep_minutes), self.data.streak)
+            }
+            None => String::new(),
+        }
+    }
+
+    fn built_in_obs_text(&self) -> String {
+        match &self.data.latest {
+            Some(sleep) => format!(
+                "Today: {}{}{}",
+                format_sleep_minutes(sleep.daily_minutes),
+                goal_lines(self.progress(), format_sleep_minutes),
+                streak_line(self.data.streak)
+            ),
+            None => NO_DATA.to_string(),
+        }
+    }
+
+    fn totals(&self) -> Option<Totals> {
+        self.data.latest.as_ref().map(|sleep| Totals {
+            daily: sleep.daily_minutes,
+            monthly: sleep.monthly_minutes,
+            yearly: sleep.yearly_minutes,
+        })
+    }
+
+    shared_metric_methods!();
+}
+
+/// Format a count compactly, e.g. `8.42K` or `1.23M`.
+pub fn format_number(n: i64) -> String {
+    if n >= 1_000_000 {
+        format!("{:.2}M", n as f64 / 1_000_000.0)
+    } else if n >= 1_000 {
+        format!("{:.2}K", n as f64 / 1_000.0)
+    } else {
+        format!("{}", n)
+    }
+}
+
+/// Format a count like [`format_number`] but without trailing zeros, e.g.
+/// `10K` or `8.4K`.
+pub fn format_short(n: i64) -> String {
+    let formatted = format_number(n);
+    match formatted.strip_suffix(['K', 'M']) {
+        Some(number) => {
+            let suffix = &formatted[number.len()..];
+            format!("{}{}", number.trim_end_matches('0').trim_end_matches('.'), suffix)
+        }
+        None => formatted,
+    }
+}
+
+/// Format millilitres the way the water summary displays them, e.g. `750 ml`
+/// or `1.5 L`.
+pub fn format_volume(ml: i64) -> String {
+    if ml.abs() < 1000 {
+        return format!("{} ml", ml);
+    }
+    // Round to a tenth of a litre in integers, so 2250 ml is 2.3 L
+    let sign = if ml < 0 { "-" } else { "" };
+    let tenths = (ml.abs() + 50) / 100;
+    match tenths % 10 {
+        0 => format!("{}{} L", sign, tenths / 10),
+        rest => format!("{}{}.{} L", sign, tenths / 10, rest),
+    }
+}
+
+/// Format minutes as hours and minutes, e.g. `7h 30m`.
+pub fn format_sleep_minutes(minutes: i64) -> String {
+    let hours = minutes / 60;
+    let mins = minutes % 60;
+    if hours > 0 {
+        if mins > 0 {
+            format!("{}h {}m", hours, mins)
+        } else {
+            format!("{}h", hours)
+        }
+    } else {
+        format!("{}m", mins)
+    }
+}
+
+// Calculate total minutes since the start of the year
+fn minutes_since_year_start() -> i64 {
+    let now = Local::now();
+    let year_start = Local.with_ymd_and_hms(now.year(), 1, 1, 0, 0, 0)
+        .single()
+        .expect("Failed to create year start");
+    let duration = now.signed_duration_since(year_start);
+    duration.num_minutes()
+}
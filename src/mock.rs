@@ -0,0 +1,356 @@
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Request, Response, Server};
+
+// Token handed out by the login and register endpoints when none is required
+const DEFAULT_TOKEN: &str = "mock-token";
+
+/// A canned answer of [`MockApi`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: Value,
+    /// Added to the server-wide latency before answering.
+    pub delay: Duration,
+}
+
+impl MockResponse {
+    /// `200 OK` with `body`.
+    pub fn ok(body: Value) -> Self {
+        Self::json(200, body)
+    }
+
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            body,
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// `status` with an `{"error": message}` body, as the real API sends.
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// A request received by [`MockApi`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub authorization: Option<String>,
+}
+
+/// One entry of a script file loaded with [`MockApi::load_script`].
+#[derive(Debug, Deserialize)]
+pub struct ScriptEntry {
+    pub path: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Response body; defaults to `{"error": "scripted failure"}` for
+    /// non-success statuses and `{}` otherwise.
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Serve this response this many times before falling back. Without it
+    /// the entry replaces the endpoint's default response.
+    pub times: Option<usize>,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl ScriptEntry {
+    fn response(&self) -> MockResponse {
+        let body = match self.body {
+            Some(ref body) => body.clone(),
+            None if self.status >= 400 => json!({ "error": "scripted failure" }),
+            None => json!({}),
+        };
+        MockResponse::json(self.status, body).with_delay(Duration::from_millis(self.delay_ms))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    #[serde(default, rename = "response")]
+    responses: Vec<ScriptEntry>,
+}
+
+#[derive(Default)]
+struct Route {
+    default: Option<MockResponse>,
+    queue: VecDeque<MockResponse>,
+}
+
+struct State {
+    routes: HashMap<String, Route>,
+    token: Option<String>,
+    latency: Duration,
+    failure_rate: f64,
+    logging: bool,
+    requests: Vec<RecordedRequest>,
+}
+
+/// A local stand-in for the step tracker API.
+///
+/// Serves the summary endpoints with sample data, plus login and register.
+/// Responses can be replaced or scripted per endpoint, and latency and
+/// random failures can be injected for every request. Stops when dropped.
+pub struct MockApi {
+    server: Arc<Server>,
+    port: u16,
+    state: Arc<Mutex<State>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockApi {
+    /// Listen on `addr`, e.g. `127.0.0.1:0` for a free port.
+    pub fn start(addr: &str) -> io::Result<Self> {
+        let server = Server::http(addr).map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, e.to_string()))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an IP address"))?;
+        let server = Arc::new(server);
+
+        let mut routes = HashMap::new();
+        for (path, body) in sample_data() {
+            routes.insert(
+                path.to_string(),
+                Route {
+                    default: Some(MockResponse::ok(body)),
+                    queue: VecDeque::new(),
+                },
+            );
+        }
+        let state = Arc::new(Mutex::new(State {
+            routes,
+            token: None,
+            latency: Duration::ZERO,
+            failure_rate: 0.0,
+            logging: false,
+            requests: Vec::new(),
+        }));
+
+        let thread = {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let state = Arc::clone(&state);
+                    // Answer concurrently so one slow response doesn't hold up the rest
+                    thread::spawn(move || handle(request, &state));
+                }
+            })
+        };
+
+        Ok(Self {
+            server,
+            port,
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    /// Base URL to use as `API_URL`.
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Answer every request for `path` with `response`, after any scripted ones.
+    pub fn set_response(&self, path: &str, response: MockResponse) {
+        self.route(path, |route| route.default = Some(response));
+    }
+
+    /// Answer the next requests for `path` with `responses`, in order.
+    pub fn push_responses(&self, path: &str, responses: impl IntoIterator<Item = MockResponse>) {
+        self.route(path, |route| route.queue.extend(responses));
+    }
+
+    /// Require this token on every endpoint except login and register, and
+    /// hand it out from them.
+    pub fn require_token(&self, token: Option<&str>) {
+        self.state.lock().unwrap().token = token.map(str::to_string);
+    }
+
+    /// Delay every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Answer this fraction of requests (0.0 to 1.0) with HTTP 500.
+    pub fn set_failure_rate(&self, rate: f64) {
+        self.state.lock().unwrap().failure_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// Print a line for every request served.
+    pub fn set_logging(&self, logging: bool) {
+        self.state.lock().unwrap().logging = logging;
+    }
+
+    /// Apply a TOML script: a list of `[[response]]` tables, see [`ScriptEntry`].
+    pub fn load_script(&self, script: &str) -> Result<(), toml::de::Error> {
+        let script: Script = toml::from_str(script)?;
+        for entry in script.responses {
+            let response = entry.response();
+            match entry.times {
+                Some(times) => self.push_responses(&entry.path, std::iter::repeat_n(response, times)),
+                None => self.set_response(&entry.path, response),
+            }
+        }
+        Ok(())
+    }
+
+    /// Every request received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// How many requests were made for `path`.
+    pub fn request_count(&self, path: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+
+    /// Block until the server is stopped, e.g. by Ctrl+C.
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn route(&self, path: &str, update: impl FnOnce(&mut Route)) {
+        update(self.state.lock().unwrap().routes.entry(path.to_string()).or_default());
+    }
+}
+
+impl Drop for MockApi {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// What a freshly started mock serves
+fn sample_data() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "/api/steps/summary",
+            json!({ "daily": 8421, "monthly": 200_000, "yearly": 1_500_000 }),
+        ),
+        (
+            "/api/water/summary",
+            json!({
+                "daily_ml": 1500,
+                "monthly_ml": 30_000,
+                "yearly_ml": 300_000,
+                "daily_display": "1.5 L",
+                "monthly_display": "30 L",
+                "yearly_display": "300 L",
+            }),
+        ),
+        (
+            "/api/sleep/summary",
+            json!({ "daily_minutes": 450, "monthly_minutes": 9000, "yearly_minutes": 90_000 }),
+        ),
+    ]
+}
+
+fn handle(request: Request, state: &Mutex<State>) {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (request.url().to_string(), None),
+    };
+    let recorded = RecordedRequest {
+        method: request.method().to_string(),
+        path,
+        query,
+        authorization: request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.to_string()),
+    };
+
+    let (response, latency, logging) = {
+        let mut state = state.lock().unwrap();
+        let response = respond(&mut state, &recorded);
+        state.requests.push(recorded.clone());
+        (response, state.latency, state.logging)
+    };
+
+    thread::sleep(latency + response.delay);
+    if logging {
+        println!("{} {} -> {}", recorded.method, recorded.path, response.status);
+    }
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let _ = request.respond(
+        Response::from_string(response.body.to_string())
+            .with_status_code(response.status)
+            .with_header(content_type),
+    );
+}
+
+fn respond(state: &mut State, request: &RecordedRequest) -> MockResponse {
+    if state.failure_rate > 0.0 && rand::thread_rng().gen_bool(state.failure_rate) {
+        return MockResponse::error(500, "injected failure");
+    }
+
+    let auth_endpoint = matches!(request.path.as_str(), "/api/auth/login" | "/api/auth/register");
+    if auth_endpoint {
+        if let Some(route) = state.routes.get_mut(&request.path) {
+            if let Some(response) = route.queue.pop_front().or_else(|| route.default.clone()) {
+                return response;
+            }
+        }
+        let token = state.token.as_deref().unwrap_or(DEFAULT_TOKEN);
+        return MockResponse::ok(json!({ "token": token }));
+    }
+
+    if let Some(ref token) = state.token {
+        let header = request.authorization.as_deref() == Some(format!("Bearer {}", token).as_str());
+        let query = request
+            .query
+            .as_deref()
+            .is_some_and(|query| query.split('&').any(|pair| pair == format!("token={}", token)));
+        if !header && !query {
+            return MockResponse::error(401, "Invalid token");
+        }
+    }
+
+    match state.routes.get_mut(&request.path) {
+        Some(route) => route
+            .queue
+            .pop_front()
+            .or_else(|| route.default.clone())
+            .unwrap_or_else(|| MockResponse::error(404, "Not found")),
+        None => MockResponse::error(404, "Not found"),
+    }
+}
@@ -20,6 +20,8 @@ pub struct AuthResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StepsRequest {
     pub steps: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +30,32 @@ pub struct StepsResponse {
     pub date: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaterRequest {
+    pub ml: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaterResponse {
+    pub ml: i64,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SleepRequest {
+    pub minutes: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SleepEntryResponse {
+    pub minutes: i64,
+    pub date: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepsSummaryResponse {
     pub daily: i64,
@@ -52,6 +80,26 @@ pub struct SleepResponse {
     pub yearly_minutes: i64,
 }
 
+/// One day's total in a metric's unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyValue {
+    pub date: String,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyHistoryResponse {
+    pub days: Vec<DailyValue>,
+}
+
+/// Day, month and year totals of a tracked value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Totals {
+    pub daily: i64,
+    pub monthly: i64,
+    pub yearly: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
@@ -0,0 +1,21 @@
+use crate::metrics::Metric;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Write a metric's latest data to its OBS text file, if it has one.
+pub fn write_metric(metric: &dyn Metric) -> io::Result<()> {
+    match metric.options().obs_file {
+        Some(ref file_path) => write(file_path, &metric.obs_text()),
+        None => Ok(()),
+    }
+}
+
+/// Write `text` to `file_path`, creating parent directories as needed.
+pub fn write(file_path: &Path, text: &str) -> io::Result<()> {
+    // Create parent directory if it doesn't exist
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file_path, text)
+}
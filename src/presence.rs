@@ -0,0 +1,439 @@
+use crate::achievements::Unlock;
+use crate::error::Error;
+use crate::ipc::{ConnectionState, IpcClient, IpcError};
+use crate::metrics::Metric;
+use crate::template::Template;
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Everything sent to Discord for one presence update.
+///
+/// Field names follow Discord's `SET_ACTIVITY` payload, so the JSON rendering
+/// matches what the client would have sent. Discord rejects empty strings,
+/// so empty details, state and hover text are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Activity {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub details: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<Timestamps>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets: Option<Assets>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<Button>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Timestamps {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Assets {
+    pub large_image: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub large_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub small_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub small_text: Option<String>,
+}
+
+/// A small image shown over the large one, with its own hover text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    pub image_key: String,
+    /// Hover text, rendered with the metric's template variables.
+    pub text: Option<Template>,
+}
+
+/// The badges a metric shows depending on its state. The first one that
+/// applies and is set wins: stale, goal met, streak, then the default one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Badges {
+    /// While the API is unavailable and the last values are shown.
+    pub stale: Option<Badge>,
+    /// Once the day's goal is met.
+    pub goal_met: Option<Badge>,
+    /// While a streak is going.
+    pub streak: Option<Badge>,
+    /// When nothing else applies.
+    pub default: Option<Badge>,
+}
+
+/// Most buttons Discord shows on an activity.
+pub const MAX_BUTTONS: usize = 2;
+/// Longest button label Discord accepts, in characters.
+pub const MAX_BUTTON_LABEL_LEN: usize = 32;
+/// Longest button URL Discord accepts, in characters.
+pub const MAX_BUTTON_URL_LEN: usize = 512;
+
+/// A clickable link shown under the activity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Button {
+    pub label: String,
+    pub url: String,
+}
+
+impl Button {
+    /// A button, checked against Discord's limits so it isn't rejected later.
+    pub fn new(label: &str, url: &str) -> Result<Self, ButtonError> {
+        let label = label.trim();
+        let url = url.trim();
+        if label.is_empty() {
+            return Err(ButtonError::EmptyLabel);
+        }
+        let length = label.chars().count();
+        if length > MAX_BUTTON_LABEL_LEN {
+            return Err(ButtonError::LabelTooLong(length));
+        }
+        let has_host = ["http://", "https://"]
+            .iter()
+            .any(|scheme| url.strip_prefix(scheme).is_some_and(|rest| !rest.is_empty()));
+        if !has_host || url.contains(char::is_whitespace) {
+            return Err(ButtonError::InvalidUrl(url.to_string()));
+        }
+        let length = url.chars().count();
+        if length > MAX_BUTTON_URL_LEN {
+            return Err(ButtonError::UrlTooLong(length));
+        }
+        Ok(Self {
+            label: label.to_string(),
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Why a button would be rejected by Discord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ButtonError {
+    EmptyLabel,
+    LabelTooLong(usize),
+    InvalidUrl(String),
+    UrlTooLong(usize),
+}
+
+impl fmt::Display for ButtonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ButtonError::EmptyLabel => write!(f, "label must not be empty"),
+            ButtonError::LabelTooLong(length) => write!(
+                f,
+                "label is {} characters long (at most {} are allowed)",
+                length, MAX_BUTTON_LABEL_LEN
+            ),
+            ButtonError::InvalidUrl(url) => write!(f, "url '{}' must be an http:// or https:// link", url),
+            ButtonError::UrlTooLong(length) => write!(
+                f,
+                "url is {} characters long (at most {} are allowed)",
+                length, MAX_BUTTON_URL_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ButtonError {}
+
+impl Activity {
+    /// The regular activity for a metric, spanning the current day.
+    pub fn for_metric(metric: &dyn Metric) -> Self {
+        Self::with_badge(metric, false)
+    }
+
+    // The small image is picked from the metric's badges, see Metric::badge()
+    fn with_badge(metric: &dyn Metric, stale: bool) -> Self {
+        let image_key = metric.image_key();
+        let badge = metric.badge(stale);
+        Self {
+            details: metric.details(),
+            state: metric.state(),
+            timestamps: Some(day_timestamps()),
+            assets: (!image_key.is_empty()).then(|| Assets {
+                large_image: image_key.to_string(),
+                large_text: metric.hover_text(),
+                small_image: badge.map(|badge| badge.image_key.clone()),
+                small_text: badge
+                    .and_then(|badge| metric.render(badge.text.as_ref()))
+                    .filter(|text| !text.is_empty()),
+            }),
+            buttons: metric.options().buttons.clone(),
+        }
+    }
+
+    /// Shown briefly when a metric unlocks an achievement, with `image_key`
+    /// or the metric's own image if that is empty.
+    pub fn achievement(metric: &dyn Metric, unlock: &Unlock, image_key: &str) -> Self {
+        let image_key = if image_key.is_empty() { metric.image_key() } else { image_key };
+        Self {
+            details: "🏆 Achievement unlocked!".to_string(),
+            state: unlock.title.clone(),
+            timestamps: None,
+            assets: (!image_key.is_empty()).then(|| Assets {
+                large_image: image_key.to_string(),
+                large_text: unlock.title.clone(),
+                ..Assets::default()
+            }),
+            buttons: metric.options().buttons.clone(),
+        }
+    }
+
+    /// Shown when a metric could not be fetched: the last good values marked
+    /// as stale, or a placeholder if nothing was ever fetched.
+    pub fn stale(metric: &dyn Metric) -> Self {
+        match metric.fetched_at() {
+            Some(fetched_at) => {
+                let mut activity = Self::with_badge(metric, true);
+                // Data cached on an earlier day needs its date, not just the time
+                let as_of = if fetched_at.date_naive() == Local::now().date_naive() {
+                    fetched_at.format("%H:%M")
+                } else {
+                    fetched_at.format("%b %-d %H:%M")
+                };
+                let marker = format!("(stale, as of {})", as_of);
+                activity.state = if activity.state.is_empty() {
+                    marker
+                } else {
+                    format!("{} {}", activity.state, marker)
+                };
+                activity
+            }
+            None => Self {
+                details: format!("Waiting for {} data", metric.label().to_lowercase()),
+                state: "The API is unreachable right now".to_string(),
+                timestamps: None,
+                assets: None,
+                buttons: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Start and end of the current local day as Unix timestamps.
+pub fn day_timestamps() -> Timestamps {
+    let now = Local::now();
+    let today = now.date_naive();
+
+    // Start timestamp: today at 00:00:00 (midnight start of day)
+    let today_start = Local.from_local_datetime(&today.and_hms_opt(0, 0, 0).unwrap())
+        .single()
+        .expect("Failed to create today midnight");
+
+    // End timestamp: today at 23:59:59 (end of day)
+    // Since this runs every update, if we're past 23:59:59,
+    // now.date_naive() will already be the next day, so this will be correct
+    let today_end = Local.from_local_datetime(&today.and_hms_opt(23, 59, 59).unwrap())
+        .single()
+        .expect("Failed to create today end");
+
+    Timestamps {
+        start: today_start.timestamp() as u64,
+        end: today_end.timestamp() as u64,
+    }
+}
+
+/// A Discord client that could not be reached or stopped answering.
+#[derive(Debug)]
+pub struct DiscordError {
+    /// Label of the affected client, e.g. `Steps`.
+    pub client: String,
+    pub source: IpcError,
+}
+
+impl fmt::Display for DiscordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Discord RPC: {}", self.client, self.source)
+    }
+}
+
+impl std::error::Error for DiscordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Somewhere to show activities, one slot per metric in rotation order.
+pub trait Presence {
+    /// Show `activity` for the metric at `slot`, hiding the other slots.
+    fn show(&mut self, slot: usize, activity: &Activity) -> Result<(), Error>;
+}
+
+// One Discord application connection and the name it is logged under
+struct DiscordClient {
+    label: String,
+    ipc: IpcClient,
+}
+
+impl DiscordClient {
+    fn new(client_id: u64, label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ipc: IpcClient::new(client_id),
+        }
+    }
+
+    fn connect(&mut self, timeout: Duration) -> Result<(), DiscordError> {
+        match self.ipc.connect(timeout) {
+            Ok(()) => {
+                println!("{} Discord RPC connected!", self.label);
+                Ok(())
+            }
+            Err(source) => Err(self.error(source)),
+        }
+    }
+
+    fn error(&self, source: IpcError) -> DiscordError {
+        DiscordError { client: self.label.clone(), source }
+    }
+
+    // Set the activity, connecting first if needed. A connection that turns
+    // out to be dead, e.g. because Discord restarted, is reconnected once and
+    // the activity sent again
+    fn set_activity(&mut self, activity: &Activity, timeout: Duration) -> Result<(), DiscordError> {
+        let was_connected = self.ipc.state() == ConnectionState::Connected;
+        if !was_connected {
+            self.connect(timeout)?;
+        }
+        match self.ipc.set_activity(activity) {
+            Err(IpcError::Io(_) | IpcError::Closed { .. }) if was_connected => {
+                self.connect(timeout)?;
+                self.ipc.set_activity(activity).map_err(|e| self.error(e))
+            }
+            result => result.map_err(|e| self.error(e)),
+        }
+    }
+}
+
+/// Shows activities through Discord, either with one application per metric
+/// or with a single application shared by all of them.
+///
+/// Each application has its own connection: one that drops is reconnected
+/// the next time its slot is shown, without touching the others, and the
+/// update that found it dropped is sent again.
+pub struct DiscordPresence {
+    clients: Vec<DiscordClient>,
+    single: bool,
+    connect_timeout: Duration,
+}
+
+impl DiscordPresence {
+    /// One client per metric, not connected yet. Discord is given
+    /// `connect_timeout` to answer each handshake.
+    pub fn new(metrics: &[Box<dyn Metric>], connect_timeout: Duration) -> Self {
+        let clients = metrics
+            .iter()
+            .map(|metric| DiscordClient::new(metric.options().client_id, metric.label()))
+            .collect();
+        Self { clients, single: false, connect_timeout }
+    }
+
+    /// A single client that every slot is shown through, not connected yet.
+    pub fn new_single(client_id: u64, connect_timeout: Duration) -> Self {
+        let clients = vec![DiscordClient::new(client_id, "Shared")];
+        Self { clients, single: true, connect_timeout }
+    }
+
+    /// Connect through the socket at `path` instead of searching for Discord.
+    pub fn with_socket_path(mut self, path: &Path) -> Self {
+        for client in self.clients.iter_mut() {
+            let ipc = IpcClient::new(client.ipc.client_id());
+            client.ipc = ipc.with_socket_path(path);
+        }
+        self
+    }
+
+    /// Try to connect every client that isn't connected.
+    pub fn connect(&mut self) {
+        for client in self.clients.iter_mut() {
+            if client.ipc.state() == ConnectionState::Connected {
+                continue;
+            }
+            // Clients that can't connect yet are retried when next shown
+            if let Err(e) = client.connect(self.connect_timeout) {
+                eprintln!("⚠️  {}", e);
+            }
+        }
+    }
+
+    fn client_index(&self, slot: usize) -> usize {
+        if self.single { 0 } else { slot }
+    }
+}
+
+impl Presence for DiscordPresence {
+    fn show(&mut self, slot: usize, activity: &Activity) -> Result<(), Error> {
+        let index = self.client_index(slot);
+        let result = self.clients[index].set_activity(activity, self.connect_timeout);
+
+        // Clear other activities even if this one could not be shown (a
+        // single client has none). A client that fails here is reconnected
+        // when its own slot comes up.
+        for (i, other) in self.clients.iter_mut().enumerate() {
+            if i != index && other.ipc.state() == ConnectionState::Connected {
+                let _ = other.ipc.clear_activity();
+            }
+        }
+        Ok(result?)
+    }
+}
+
+/// Renders each activity as a line of JSON instead of talking to Discord.
+pub struct DryRunPresence<W: Write> {
+    out: W,
+    client_ids: Vec<u64>,
+    metric_ids: Vec<String>,
+}
+
+impl DryRunPresence<io::Stdout> {
+    pub fn stdout(metrics: &[Box<dyn Metric>]) -> Self {
+        Self::new(io::stdout(), metrics)
+    }
+}
+
+impl<W: Write> DryRunPresence<W> {
+    pub fn new(out: W, metrics: &[Box<dyn Metric>]) -> Self {
+        Self {
+            out,
+            client_ids: metrics.iter().map(|m| m.options().client_id).collect(),
+            metric_ids: metrics.iter().map(|m| m.id().to_string()).collect(),
+        }
+    }
+
+    /// Report every slot as shown through one application, as in single mode.
+    pub fn single(mut self, client_id: u64) -> Self {
+        self.client_ids.iter_mut().for_each(|id| *id = client_id);
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+#[derive(Serialize)]
+struct DryRunRecord<'a> {
+    metric: &'a str,
+    client_id: u64,
+    activity: &'a Activity,
+}
+
+impl<W: Write> Presence for DryRunPresence<W> {
+    fn show(&mut self, slot: usize, activity: &Activity) -> Result<(), Error> {
+        let record = DryRunRecord {
+            metric: &self.metric_ids[slot],
+            client_id: self.client_ids[slot],
+            activity,
+        };
+        // An activity always serializes, so only the write itself can fail
+        serde_json::to_writer(&mut self.out, &record).map_err(io::Error::from)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// Exponential backoff with jitter for retrying failed requests.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Upper bound of the delay before retry number `retry` (starting at 0).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Delay before retry number `retry`: a random duration between half the
+    /// backoff and the whole of it, so clients that failed together don't
+    /// retry together.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests are refused until the cooldown ends.
+    Open { until: Instant },
+    /// The cooldown ended; the next request decides whether to close again.
+    HalfOpen,
+}
+
+/// Stops calling a server after repeated failures, then probes it again
+/// after a cooldown that doubles each time the probe fails.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    base_cooldown: Duration,
+    max_cooldown: Duration,
+    cooldown: Duration,
+    failures: u32,
+    state: CircuitState,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60), Duration::from_secs(15 * 60))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration, max_cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            base_cooldown: cooldown,
+            max_cooldown: max_cooldown.max(cooldown),
+            cooldown,
+            failures: 0,
+            state: CircuitState::Closed,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// `Err` with the remaining cooldown if requests should not be sent now.
+    pub fn check(&mut self) -> Result<(), Duration> {
+        if let CircuitState::Open { until } = self.state {
+            let now = Instant::now();
+            if now < until {
+                return Err(until - now);
+            }
+            self.state = CircuitState::HalfOpen;
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.cooldown = self.base_cooldown;
+        self.state = CircuitState::Closed;
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+        match self.state {
+            CircuitState::HalfOpen => {
+                // The probe failed: stay away for longer this time
+                self.cooldown = self.cooldown.saturating_mul(2).min(self.max_cooldown);
+                self.open();
+            }
+            CircuitState::Closed if self.failures >= self.failure_threshold => self.open(),
+            _ => {}
+        }
+    }
+
+    fn open(&mut self) {
+        self.state = CircuitState::Open {
+            until: Instant::now() + self.cooldown,
+        };
+    }
+}
@@ -0,0 +1,256 @@
+use crate::achievements::Achievements;
+use crate::api::{ApiClient, ApiError};
+use crate::cache::Cache;
+use crate::control;
+use crate::error::Error;
+use crate::history::History;
+use crate::metrics::Metric;
+use crate::obs;
+use crate::presence::{Activity, Presence};
+use crate::streak::{self, PastDays};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Where status lines such as "Switching to Steps RPC..." are printed.
+///
+/// A dry run prints activities as JSON on stdout, so its status lines go to
+/// stderr to keep stdout parseable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusOutput {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+impl StatusOutput {
+    pub fn print(self, args: fmt::Arguments) {
+        match self {
+            StatusOutput::Stdout => println!("{}", args),
+            StatusOutput::Stderr => eprintln!("{}", args),
+        }
+    }
+}
+
+/// Cycles through metrics, showing each for its display duration and
+/// refetching it once its fetch interval has passed.
+pub struct Rotation {
+    metrics: Vec<Box<dyn Metric>>,
+    current: usize,
+    last_fetch: Vec<Option<Instant>>,
+    cache: Option<Cache>,
+    history: Option<History>,
+    past_days: Vec<PastDays>,
+    achievements: Option<Achievements>,
+    // Unlocked achievements still to be shown, with the slot of their metric
+    celebrations: VecDeque<(usize, Activity)>,
+    status: StatusOutput,
+}
+
+impl Rotation {
+    pub fn new(metrics: Vec<Box<dyn Metric>>) -> Self {
+        let last_fetch = vec![None; metrics.len()];
+        let past_days = vec![PastDays::default(); metrics.len()];
+        Self {
+            metrics,
+            current: 0,
+            last_fetch,
+            cache: None,
+            history: None,
+            past_days,
+            achievements: None,
+            celebrations: VecDeque::new(),
+            status: StatusOutput::default(),
+        }
+    }
+
+    /// Save the data of every fetch in `cache`, to fall back on next time.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Record every fetched value in `history`.
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Check every fetch for achievements and show the ones unlocked.
+    pub fn with_achievements(mut self, achievements: Achievements) -> Self {
+        self.achievements = Some(achievements);
+        self
+    }
+
+    /// Print status lines to `status` instead of stdout.
+    pub fn with_status_output(mut self, status: StatusOutput) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn achievements(&self) -> Option<&Achievements> {
+        self.achievements.as_ref()
+    }
+
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    pub fn metrics(&self) -> &[Box<dyn Metric>] {
+        &self.metrics
+    }
+
+    /// Start again from the first metric on the next step, refetching everything.
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.celebrations.clear();
+        self.last_fetch.iter_mut().for_each(|last| *last = None);
+    }
+
+    // Whether the metric's data is older than its fetch interval
+    fn needs_fetch(&self, slot: usize) -> bool {
+        match self.last_fetch[slot] {
+            Some(last) => last.elapsed() >= self.metrics[slot].options().fetch_interval,
+            None => true,
+        }
+    }
+
+    /// Show the current metric, fetching it first if its data is due, then
+    /// move on to the next one. Returns how long the metric should stay shown.
+    ///
+    /// Achievements unlocked by a fetch are shown first, one per step, and
+    /// the metric itself comes up again once they have all been shown.
+    ///
+    /// Fetch failures fall back to stale data and Discord failures are left
+    /// to the presence to recover from; a rejected token is returned.
+    pub fn step(&mut self, api: &ApiClient, presence: &mut dyn Presence) -> Result<Duration, Error> {
+        if self.metrics.is_empty() {
+            return Ok(Duration::ZERO);
+        }
+        if let Some(duration) = self.celebrate(presence)? {
+            return Ok(duration);
+        }
+
+        let slot = self.current;
+        let status = self.status;
+        let needs_fetch = self.needs_fetch(slot);
+        let metric = self.metrics[slot].as_mut();
+        status.print(format_args!("🔄 Switching to {} RPC...", metric.label()));
+
+        let activity = if !needs_fetch {
+            status.print(format_args!(
+                "Reusing {} data fetched less than {}s ago",
+                metric.id(),
+                metric.options().fetch_interval.as_secs()
+            ));
+            Activity::for_metric(metric)
+        } else {
+            match metric.fetch(api) {
+                Ok(()) => {
+                    self.last_fetch[slot] = Some(Instant::now());
+                    status.print(format_args!("Fetched {} - {} | {}", metric.id(), metric.details(), metric.state()));
+                    if let Some(ref cache) = self.cache {
+                        if let Err(e) = cache.store(metric) {
+                            eprintln!("Failed to cache {} data: {}", metric.id(), e);
+                        }
+                    }
+                    if let Some(ref history) = self.history {
+                        if let Err(e) = history.record(metric) {
+                            eprintln!("Failed to record {} history: {}", metric.id(), e);
+                        }
+                    }
+                    streak::update(metric, api, self.history.as_ref(), &mut self.past_days[slot]);
+                    if let Some(ref mut achievements) = self.achievements {
+                        match achievements.check(metric) {
+                            Ok(unlocked) => {
+                                for unlock in unlocked {
+                                    let activity = Activity::achievement(metric, &unlock, achievements.image_key());
+                                    self.celebrations.push_back((slot, activity));
+                                }
+                            }
+                            Err(e) => eprintln!("Failed to save achievements: {}", e),
+                        }
+                    }
+                    match obs::write_metric(metric) {
+                        Ok(()) => {
+                            if let Some(ref file_path) = metric.options().obs_file {
+                                status.print(format_args!("✅ OBS {} data written to {}", metric.id(), file_path.display()));
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to write OBS {} file: {}", metric.id(), e),
+                    }
+                    Activity::for_metric(metric)
+                }
+                // Retrying won't help until the user logs in again
+                Err(e @ ApiError::Unauthorized { .. }) => return Err(e.into()),
+                Err(e) => {
+                    eprintln!("Error fetching {}: {}", metric.id(), e);
+                    Activity::stale(metric)
+                }
+            }
+        };
+
+        if let Some(duration) = self.celebrate(presence)? {
+            return Ok(duration);
+        }
+
+        // Move to next metric
+        self.current = (slot + 1) % self.metrics.len();
+
+        show(presence, slot, &activity)?;
+        Ok(self.metrics[slot].options().display_duration)
+    }
+
+    // Show the next unlocked achievement, if any, returning how long it stays
+    fn celebrate(&mut self, presence: &mut dyn Presence) -> Result<Option<Duration>, Error> {
+        let (Some((slot, activity)), Some(achievements)) = (self.celebrations.front(), self.achievements.as_ref())
+        else {
+            return Ok(None);
+        };
+        self.status.print(format_args!("🏆 Achievement unlocked: {}", activity.state));
+        show(presence, *slot, activity)?;
+        let duration = achievements.duration();
+        self.celebrations.pop_front();
+        Ok(Some(duration))
+    }
+
+    /// Show the last known values of the current metric, e.g. restored from
+    /// the cache, right away as stale data so the first fetch isn't waited
+    /// on. Only one metric is ever shown at a time, so the others wait for
+    /// their turn in the rotation.
+    pub fn show_last_known(&self, presence: &mut dyn Presence) -> Result<(), Error> {
+        let Some(metric) = self.metrics.get(self.current) else {
+            return Ok(());
+        };
+        if metric.fetched_at().is_some() {
+            show(presence, self.current, &Activity::stale(metric.as_ref()))?;
+        }
+        Ok(())
+    }
+
+    /// Step forever, waiting between updates or until a refresh is requested.
+    pub fn run(&mut self, api: &ApiClient, presence: &mut dyn Presence) -> Result<(), Error> {
+        self.show_last_known(presence)?;
+        loop {
+            let display_duration = self.step(api, presence)?;
+
+            // Keep the metric shown, or move on early if a refresh is requested
+            if control::wait_for_refresh(display_duration) {
+                self.status.print(format_args!("🔄 Refresh requested"));
+                self.restart();
+            }
+        }
+    }
+}
+
+// Show an activity, carrying on if Discord is unavailable: the presence
+// reconnects on its own and the next update goes through once it has
+fn show(presence: &mut dyn Presence, slot: usize, activity: &Activity) -> Result<(), Error> {
+    match presence.show(slot, activity) {
+        Err(Error::Discord(e)) => {
+            eprintln!("⚠️  {}", e);
+            Ok(())
+        }
+        result => result,
+    }
+}
@@ -0,0 +1,424 @@
+use crate::metrics::format_volume;
+use crate::models::*;
+use crate::store::{Series, Store, StoreError};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{Duration, Local, NaiveDate};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Method, Request, Response};
+
+// Request bodies are tiny JSON objects; anything bigger is refused
+const MAX_BODY_LEN: u64 = 64 * 1024;
+
+const MIN_PASSWORD_LEN: usize = 8;
+const MAX_USERNAME_LEN: usize = 32;
+
+// Requests served at once; the others wait in the listener's queue
+const WORKERS: usize = 8;
+
+// Longest range served by the history endpoints, enough for a year plus a day
+const MAX_HISTORY_DAYS: i64 = 366;
+
+// A status code and the message sent as `{"error": ...}`
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(400, message)
+    }
+
+    fn unauthorized(message: &str) -> Self {
+        Self::new(401, message)
+    }
+}
+
+impl From<StoreError> for HttpError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::UsernameTaken => HttpError::new(409, "Username is already taken"),
+            StoreError::Sqlite(e) => {
+                eprintln!("❌ {}", e);
+                HttpError::new(500, "Internal server error")
+            }
+        }
+    }
+}
+
+type Reply = Result<(u16, Value), HttpError>;
+
+/// The self-hosted step tracker API, serving the endpoints [`ApiClient`]
+/// uses from a [`Store`]. Stops when dropped.
+///
+/// [`ApiClient`]: crate::ApiClient
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    port: u16,
+    logging: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `addr`, e.g. `127.0.0.1:0` for a free port.
+    pub fn start(addr: &str, store: Store) -> io::Result<Self> {
+        let http = tiny_http::Server::http(addr)
+            .map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, e.to_string()))?;
+        let port = http
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an IP address"))?;
+        let http = Arc::new(http);
+        let store = Arc::new(Mutex::new(store));
+        let logging = Arc::new(AtomicBool::new(false));
+
+        // A fixed pool, so password hashing doesn't hold everyone else up
+        // and a flood of requests can't start unlimited threads
+        let workers = (0..WORKERS)
+            .map(|_| {
+                let http = Arc::clone(&http);
+                let store = Arc::clone(&store);
+                let logging = Arc::clone(&logging);
+                thread::spawn(move || {
+                    for request in http.incoming_requests() {
+                        handle(request, &store, logging.load(Ordering::Relaxed));
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            http,
+            port,
+            logging,
+            workers,
+        })
+    }
+
+    /// Base URL to use as `API_URL` on this machine.
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Print a line for every request served.
+    pub fn set_logging(&self, logging: bool) {
+        self.logging.store(logging, Ordering::Relaxed);
+    }
+
+    /// Block until the server is stopped, e.g. by Ctrl+C.
+    pub fn wait(mut self) {
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        // Each call wakes a single worker
+        for _ in 0..self.workers.len() {
+            self.http.unblock();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn handle(mut request: Request, store: &Mutex<Store>, logging: bool) {
+    let (status, body) = route(&mut request, store).unwrap_or_else(|e| (e.status, json!({ "error": e.message })));
+    if logging {
+        let path = request.url().split('?').next().unwrap_or_default();
+        println!("{} {} -> {}", request.method(), path, status);
+    }
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let _ = request.respond(
+        Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type),
+    );
+}
+
+fn route(request: &mut Request, store: &Mutex<Store>) -> Reply {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+    let method = request.method().clone();
+
+    match (method, path.as_str()) {
+        (Method::Post, "/api/auth/register") => register(store, read_json(request)?),
+        (Method::Post, "/api/auth/login") => login(store, read_json(request)?),
+        (Method::Get, "/api/steps/summary") => {
+            let totals = summary(request, &query, store, Series::Steps)?;
+            ok(StepsSummaryResponse {
+                daily: totals.daily,
+                monthly: totals.monthly,
+                yearly: totals.yearly,
+            })
+        }
+        (Method::Get, "/api/water/summary") => {
+            let totals = summary(request, &query, store, Series::Water)?;
+            ok(WaterSummaryResponse {
+                daily_ml: totals.daily,
+                monthly_ml: totals.monthly,
+                yearly_ml: totals.yearly,
+                daily_display: format_volume(totals.daily),
+                monthly_display: format_volume(totals.monthly),
+                yearly_display: format_volume(totals.yearly),
+            })
+        }
+        (Method::Get, "/api/sleep/summary") => {
+            let totals = summary(request, &query, store, Series::Sleep)?;
+            ok(SleepResponse {
+                daily_minutes: totals.daily,
+                monthly_minutes: totals.monthly,
+                yearly_minutes: totals.yearly,
+            })
+        }
+        (Method::Get, "/api/steps/history") => daily_history(request, &query, store, Series::Steps),
+        (Method::Get, "/api/water/history") => daily_history(request, &query, store, Series::Water),
+        (Method::Get, "/api/sleep/history") => daily_history(request, &query, store, Series::Sleep),
+        (Method::Post, "/api/steps" | "/api/steps/add") => {
+            let user_id = authenticate(request, &query, store)?;
+            let body: StepsRequest = read_json(request)?;
+            let date = parse_date(body.date.as_deref())?;
+            let steps = record(store, Series::Steps, user_id, date, body.steps, path.ends_with("/add"))?;
+            ok(StepsResponse {
+                steps,
+                date: Some(date.to_string()),
+            })
+        }
+        (Method::Post, "/api/water" | "/api/water/add") => {
+            let user_id = authenticate(request, &query, store)?;
+            let body: WaterRequest = read_json(request)?;
+            let date = parse_date(body.date.as_deref())?;
+            let ml = record(store, Series::Water, user_id, date, body.ml, path.ends_with("/add"))?;
+            ok(WaterResponse {
+                ml,
+                date: Some(date.to_string()),
+            })
+        }
+        (Method::Post, "/api/sleep") => {
+            let user_id = authenticate(request, &query, store)?;
+            let body: SleepRequest = read_json(request)?;
+            let date = parse_date(body.date.as_deref())?;
+            if body.minutes > 24 * 60 {
+                return Err(HttpError::bad_request("minutes must be at most 1440"));
+            }
+            let minutes = record(store, Series::Sleep, user_id, date, body.minutes, false)?;
+            ok(SleepEntryResponse {
+                minutes,
+                date: Some(date.to_string()),
+            })
+        }
+        (
+            _,
+            "/api/auth/register" | "/api/auth/login" | "/api/steps/summary" | "/api/water/summary"
+            | "/api/sleep/summary" | "/api/steps/history" | "/api/water/history" | "/api/sleep/history"
+            | "/api/steps" | "/api/steps/add" | "/api/water" | "/api/water/add" | "/api/sleep",
+        ) => Err(HttpError::new(405, "Method not allowed")),
+        _ => Err(HttpError::new(404, "Not found")),
+    }
+}
+
+fn ok<T: Serialize>(body: T) -> Reply {
+    let body = serde_json::to_value(body).map_err(|_| HttpError::new(500, "Internal server error"))?;
+    Ok((200, body))
+}
+
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, HttpError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_LEN + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| HttpError::bad_request(format!("Could not read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_LEN {
+        return Err(HttpError::new(413, "Request body is too large"));
+    }
+    serde_json::from_slice(&body).map_err(|e| HttpError::bad_request(format!("Invalid request body: {}", e)))
+}
+
+fn register(store: &Mutex<Store>, request: RegisterRequest) -> Reply {
+    let username = request.username.trim();
+    let valid_chars = username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if username.len() < 3 || username.len() > MAX_USERNAME_LEN || !valid_chars {
+        return Err(HttpError::bad_request(format!(
+            "Username must be 3 to {} letters, digits, '_', '-' or '.'",
+            MAX_USERNAME_LEN
+        )));
+    }
+    if request.password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(HttpError::bad_request(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(request.password.as_bytes(), &salt)
+        .map_err(|_| HttpError::new(500, "Internal server error"))?
+        .to_string();
+
+    let store = store.lock().unwrap();
+    let user_id = store.create_user(username, &password_hash)?;
+    let token = issue_token(&store, user_id)?;
+    Ok((201, json!(AuthResponse { token })))
+}
+
+fn login(store: &Mutex<Store>, request: LoginRequest) -> Reply {
+    let invalid = || HttpError::unauthorized("Invalid username or password");
+    let user = store.lock().unwrap().user(request.username.trim())?;
+
+    // Unknown users are checked against a dummy hash, so they take as long
+    // as a wrong password and the response time doesn't reveal who exists
+    let password_hash = match user {
+        Some(ref user) => user.password_hash.as_str(),
+        None => dummy_hash(),
+    };
+    let hash = PasswordHash::new(password_hash).map_err(|_| invalid())?;
+    let verified = Argon2::default()
+        .verify_password(request.password.as_bytes(), &hash)
+        .is_ok();
+    let user = user.filter(|_| verified).ok_or_else(invalid)?;
+
+    let token = issue_token(&store.lock().unwrap(), user.id)?;
+    ok(AuthResponse { token })
+}
+
+// A hash of a random password, made with the same settings as real ones
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        let password = rand::thread_rng().gen::<[u8; 16]>();
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(&password, &salt)
+            .expect("hashing a random password")
+            .to_string()
+    })
+}
+
+// Create a random token for the account; only its hash is stored
+fn issue_token(store: &Store, user_id: i64) -> Result<String, HttpError> {
+    let token = hex(&rand::thread_rng().gen::<[u8; 32]>());
+    store.insert_token(user_id, &hash_token(&token))?;
+    Ok(token)
+}
+
+fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// The account making the request, from the bearer header or `?token=`
+fn authenticate(request: &Request, query: &str, store: &Mutex<Store>) -> Result<i64, HttpError> {
+    let header = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(str::to_string);
+    let token = header
+        .or_else(|| query_param(query, "token"))
+        .ok_or_else(|| HttpError::unauthorized("Missing token"))?;
+
+    store
+        .lock()
+        .unwrap()
+        .token_user(&hash_token(token.trim()))?
+        .ok_or_else(|| HttpError::unauthorized("Invalid token"))
+}
+
+fn summary(request: &Request, query: &str, store: &Mutex<Store>, series: Series) -> Result<Totals, HttpError> {
+    let user_id = authenticate(request, query, store)?;
+    let date = parse_date(query_param(query, "date").as_deref())?;
+    Ok(store.lock().unwrap().totals(series, user_id, date)?)
+}
+
+// Daily values from `from` (a year before `to` by default) to `to` (today by default)
+fn daily_history(request: &Request, query: &str, store: &Mutex<Store>, series: Series) -> Reply {
+    let user_id = authenticate(request, query, store)?;
+    let to = parse_date(query_param(query, "to").as_deref())?;
+    let from = match query_param(query, "from") {
+        Some(from) => parse_date(Some(&from))?,
+        None => to - Duration::days(MAX_HISTORY_DAYS - 1),
+    };
+    if from > to {
+        return Err(HttpError::bad_request("from must not be after to"));
+    }
+    if (to - from).num_days() >= MAX_HISTORY_DAYS {
+        return Err(HttpError::bad_request(format!("At most {} days can be requested at once", MAX_HISTORY_DAYS)));
+    }
+
+    let days = store.lock().unwrap().daily(series, user_id, from, to)?;
+    ok(DailyHistoryResponse {
+        days: days
+            .into_iter()
+            .map(|(date, value)| DailyValue {
+                date: date.to_string(),
+                value,
+            })
+            .collect(),
+    })
+}
+
+fn record(store: &Mutex<Store>, series: Series, user_id: i64, date: NaiveDate, value: i64, add: bool) -> Result<i64, HttpError> {
+    if value < 0 {
+        return Err(HttpError::bad_request("Values must not be negative"));
+    }
+    let store = store.lock().unwrap();
+    let total = if add {
+        store.add(series, user_id, date, value)?
+    } else {
+        store.set(series, user_id, date, value)?
+    };
+    Ok(total)
+}
+
+// Parameters are dates and hex tokens, so no percent-decoding is needed
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+// `YYYY-MM-DD`, defaulting to today in the server's time zone
+fn parse_date(date: Option<&str>) -> Result<NaiveDate, HttpError> {
+    match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| HttpError::bad_request(format!("Invalid date '{}', expected YYYY-MM-DD", date))),
+        None => Ok(Local::now().date_naive()),
+    }
+}
@@ -0,0 +1,240 @@
+use crate::models::Totals;
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use std::fmt;
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY,
+        username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tokens (
+        token_hash TEXT PRIMARY KEY,
+        user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS steps (
+        user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        value INTEGER NOT NULL,
+        PRIMARY KEY (user_id, date)
+    );
+    CREATE TABLE IF NOT EXISTS water (
+        user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        value INTEGER NOT NULL,
+        PRIMARY KEY (user_id, date)
+    );
+    CREATE TABLE IF NOT EXISTS sleep (
+        user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        value INTEGER NOT NULL,
+        PRIMARY KEY (user_id, date)
+    );
+";
+
+/// Errors returned by [`Store`].
+#[derive(Debug)]
+pub enum StoreError {
+    /// Another account already uses the username.
+    UsernameTaken,
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::UsernameTaken => write!(f, "username is already taken"),
+            StoreError::Sqlite(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::UsernameTaken => None,
+            StoreError::Sqlite(e) => Some(e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sqlite(e)
+    }
+}
+
+/// A daily value tracked per user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Series {
+    Steps,
+    /// Millilitres drunk.
+    Water,
+    /// Minutes slept.
+    Sleep,
+}
+
+impl Series {
+    fn table(self) -> &'static str {
+        match self {
+            Series::Steps => "steps",
+            Series::Water => "water",
+            Series::Sleep => "sleep",
+        }
+    }
+}
+
+/// An account as stored, with its password hash.
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
+/// SQLite storage for the self-hosted server.
+///
+/// Dates are stored as `YYYY-MM-DD` text, so month and year totals are
+/// prefix matches. Only hashes of passwords and tokens are stored.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open or create the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn create_user(&self, username: &str, password_hash: &str) -> Result<i64, StoreError> {
+        let inserted = self.conn.execute(
+            "INSERT INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)",
+            params![username, password_hash, now()],
+        );
+        match inserted {
+            Ok(_) => Ok(self.conn.last_insert_rowid()),
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::ConstraintViolation => {
+                Err(StoreError::UsernameTaken)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Look up an account by username, ignoring case.
+    pub fn user(&self, username: &str) -> Result<Option<User>, StoreError> {
+        let user = self
+            .conn
+            .query_row(
+                "SELECT id, username, password_hash FROM users WHERE username = ?1",
+                params![username],
+                |row| {
+                    Ok(User {
+                        id: row.get(0)?,
+                        username: row.get(1)?,
+                        password_hash: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(user)
+    }
+
+    pub fn insert_token(&self, user_id: i64, token_hash: &str) -> Result<(), StoreError> {
+        self.conn.execute(
+            "INSERT INTO tokens (token_hash, user_id, created_at) VALUES (?1, ?2, ?3)",
+            params![token_hash, user_id, now()],
+        )?;
+        Ok(())
+    }
+
+    /// The account a token was issued to, if it exists.
+    pub fn token_user(&self, token_hash: &str) -> Result<Option<i64>, StoreError> {
+        let user_id = self
+            .conn
+            .query_row(
+                "SELECT user_id FROM tokens WHERE token_hash = ?1",
+                params![token_hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(user_id)
+    }
+
+    /// Replace the value for a day and return it.
+    pub fn set(&self, series: Series, user_id: i64, date: NaiveDate, value: i64) -> Result<i64, StoreError> {
+        let sql = format!(
+            "INSERT INTO {} (user_id, date, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id, date) DO UPDATE SET value = excluded.value",
+            series.table()
+        );
+        self.conn.execute(&sql, params![user_id, date.to_string(), value])?;
+        Ok(value)
+    }
+
+    /// Add to the value for a day and return the new total.
+    pub fn add(&self, series: Series, user_id: i64, date: NaiveDate, amount: i64) -> Result<i64, StoreError> {
+        let sql = format!(
+            "INSERT INTO {} (user_id, date, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id, date) DO UPDATE SET value = value + excluded.value
+             RETURNING value",
+            series.table()
+        );
+        let total = self
+            .conn
+            .query_row(&sql, params![user_id, date.to_string(), amount], |row| row.get(0))?;
+        Ok(total)
+    }
+
+    /// The value of each day from `from` to `to` inclusive, oldest first.
+    /// Days without a value are left out.
+    pub fn daily(&self, series: Series, user_id: i64, from: NaiveDate, to: NaiveDate) -> Result<Vec<(NaiveDate, i64)>, StoreError> {
+        let sql = format!(
+            "SELECT date, value FROM {} WHERE user_id = ?1 AND date BETWEEN ?2 AND ?3 ORDER BY date",
+            series.table()
+        );
+        let mut statement = self.conn.prepare(&sql)?;
+        let days = statement.query_map(params![user_id, from.to_string(), to.to_string()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        Ok(days.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Totals for `date`, its month and its year.
+    pub fn totals(&self, series: Series, user_id: i64, date: NaiveDate) -> Result<Totals, StoreError> {
+        let day = date.to_string();
+        let sql = format!(
+            "SELECT
+                 COALESCE(SUM(CASE WHEN date = ?2 THEN value END), 0),
+                 COALESCE(SUM(CASE WHEN substr(date, 1, 7) = substr(?2, 1, 7) THEN value END), 0),
+                 COALESCE(SUM(value), 0)
+             FROM {} WHERE user_id = ?1 AND substr(date, 1, 4) = substr(?2, 1, 4)",
+            series.table()
+        );
+        let totals = self.conn.query_row(&sql, params![user_id, day], |row| {
+            Ok(Totals {
+                daily: row.get(0)?,
+                monthly: row.get(1)?,
+                yearly: row.get(2)?,
+            })
+        })?;
+        Ok(totals)
+    }
+}
+
+fn now() -> String {
+    Local::now().to_rfc3339()
+}
@@ -0,0 +1,133 @@
+use crate::api::{ApiClient, ApiError};
+use crate::history::History;
+use crate::metrics::Metric;
+use chrono::{Duration, Local, NaiveDate};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// How many days before today are looked at when working out streaks.
+pub const WINDOW_DAYS: i64 = 365;
+
+/// Consecutive days on which a daily goal was met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streak {
+    /// Days in the streak that is still going.
+    pub current: u32,
+    /// The longest streak seen, including the current one.
+    pub longest: u32,
+}
+
+impl Streak {
+    /// Work out streaks from daily totals up to `today`.
+    ///
+    /// A day without a value, or below `goal`, breaks a streak. Today only
+    /// breaks it once it is over: until its goal is met the current streak
+    /// is the one that ended yesterday.
+    pub fn from_days(days: impl IntoIterator<Item = (NaiveDate, i64)>, goal: i64, today: NaiveDate) -> Self {
+        let met: BTreeSet<NaiveDate> = days
+            .into_iter()
+            .filter(|(date, value)| *date <= today && *value >= goal)
+            .map(|(date, _)| date)
+            .collect();
+
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &date in &met {
+            run = match previous {
+                Some(previous) if date - previous == Duration::days(1) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(date);
+        }
+
+        let mut day = if met.contains(&today) { today } else { today - Duration::days(1) };
+        let mut current = 0;
+        while met.contains(&day) {
+            current += 1;
+            day -= Duration::days(1);
+        }
+
+        Self { current, longest }
+    }
+}
+
+impl fmt::Display for Streak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "🔥 {}-day streak", self.current)
+    }
+}
+
+/// Daily totals of the days before `today` within [`WINDOW_DAYS`], from the
+/// API if it keeps a history and from the local history otherwise.
+pub fn past_days(
+    api: &ApiClient,
+    history: Option<&History>,
+    metric_id: &str,
+    today: NaiveDate,
+) -> Result<Vec<(NaiveDate, i64)>, ApiError> {
+    let from = today - Duration::days(WINDOW_DAYS);
+    let to = today - Duration::days(1);
+
+    let remote = api.daily_history(metric_id, &from.to_string(), &to.to_string());
+    let error = match remote {
+        Ok(response) => {
+            return Ok(response
+                .days
+                .into_iter()
+                .filter_map(|day| Some((day.date.parse().ok()?, day.value)))
+                .collect())
+        }
+        Err(e @ ApiError::Unauthorized { .. }) => return Err(e),
+        Err(e) => e,
+    };
+
+    match history.map(|history| history.days(metric_id, from, to)) {
+        Some(Ok(entries)) => Ok(entries.into_iter().map(|entry| (entry.date, entry.totals.daily)).collect()),
+        Some(Err(e)) => {
+            eprintln!("Failed to read {} history: {}", metric_id, e);
+            Err(error)
+        }
+        None => Err(error),
+    }
+}
+
+/// Daily totals of the days before the one they were loaded on, so they are
+/// only loaded once a day. A failed load is also only tried once a day.
+#[derive(Debug, Clone, Default)]
+pub struct PastDays {
+    loaded_on: Option<NaiveDate>,
+    // None if loading them failed
+    days: Option<Vec<(NaiveDate, i64)>>,
+}
+
+/// Work out the metric's streak from its latest daily total and the days
+/// before it, loading those first if `past` is from another day. Metrics
+/// without a goal or without data are left alone.
+pub fn update(metric: &mut dyn Metric, api: &ApiClient, history: Option<&History>, past: &mut PastDays) {
+    let (Some(goal), Some(totals)) = (metric.options().goal, metric.totals()) else {
+        return;
+    };
+    let today = Local::now().date_naive();
+
+    if past.loaded_on != Some(today) {
+        // Not asking again until tomorrow keeps a missing history endpoint
+        // from costing retries and tripping the circuit breaker on every fetch
+        past.loaded_on = Some(today);
+        past.days = match past_days(api, history, metric.id(), today) {
+            Ok(days) => Some(days),
+            Err(e) => {
+                eprintln!("Could not load {} history for streaks: {}", metric.id(), e);
+                None
+            }
+        };
+    }
+
+    // Today alone would understate the streak
+    let Some(ref past_days) = past.days else {
+        return;
+    };
+    let days = past_days.iter().copied().chain([(today, totals.daily)]);
+    metric.set_streak(Some(Streak::from_days(days, goal, today)));
+}
@@ -0,0 +1,257 @@
+use crate::metrics::{format_number, format_short, format_sleep_minutes, format_volume};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Every variable a template may use, with a short description.
+pub const VARIABLES: &[(&str, &str)] = &[
+    ("daily", "today's total"),
+    ("monthly", "this month's total"),
+    ("yearly", "this year's total"),
+    ("goal", "the daily goal"),
+    ("goal_pct", "percentage of the daily goal reached"),
+    ("remaining", "what is left to reach the daily goal"),
+    ("streak", "days in a row the goal was met"),
+    ("best_streak", "the longest streak"),
+    ("label", "the metric's name, e.g. Steps"),
+    ("metric", "the metric's id, e.g. steps"),
+    ("as_of", "when the data was fetched, as HH:MM"),
+    ("year_elapsed", "time since the start of the year"),
+];
+
+/// Every filter a template may apply, with a short description.
+pub const FILTERS: &[(&str, &str)] = &[
+    ("raw", "the plain number, e.g. 8421"),
+    ("compact", "a compact count, e.g. 8.42K"),
+    ("short", "a compact count without trailing zeros, e.g. 10K"),
+    ("comma", "a number with thousands separators, e.g. 8,421"),
+    ("unit", "the number with the metric's unit, e.g. 8421 steps"),
+    ("volume", "millilitres as ml or L, e.g. 2.5 L"),
+    ("duration", "minutes as hours and minutes, e.g. 7h 30m"),
+    ("upper", "upper case"),
+    ("lower", "lower case"),
+];
+
+/// What the amounts of a metric measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// A number of things, named in the plural, e.g. `steps`.
+    Count(&'static str),
+    Millilitres,
+    Minutes,
+}
+
+impl Unit {
+    // How amounts are shown without a filter, as in the built-in presence
+    fn format(self, value: i64) -> String {
+        match self {
+            Unit::Count(_) => format_number(value),
+            Unit::Millilitres => format_volume(value),
+            Unit::Minutes => format_sleep_minutes(value),
+        }
+    }
+
+    /// The full amount followed by its unit, e.g. `8421 steps` or `1.5 L`.
+    pub fn format_with_unit(self, value: i64) -> String {
+        match self {
+            Unit::Count(things) => format!("{} {}", value, things),
+            Unit::Millilitres => format_volume(value),
+            Unit::Minutes => format_sleep_minutes(value),
+        }
+    }
+}
+
+/// A value a template variable stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// An amount in a metric's unit.
+    Amount(i64, Unit),
+    /// A plain number, such as a percentage or a number of days.
+    Number(i64),
+    Text(String),
+}
+
+/// The values available to a template. Variables that are not set, such as
+/// `goal` for a metric without one, render as nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variables {
+    values: BTreeMap<&'static str, Value>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &'static str, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+}
+
+/// Errors found while parsing a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    UnknownVariable(String),
+    UnknownFilter(String),
+    /// A `{` without its `}`, or a lone `}`.
+    Unbalanced,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            TemplateError::UnknownFilter(name) => write!(f, "unknown filter '{}'", name),
+            TemplateError::Unbalanced => write!(f, "unbalanced braces (write {{{{ and }}}} for literal ones)"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Variable { name: String, filter: Option<String> },
+}
+
+/// A text with `{variable}` or `{variable:filter}` placeholders, e.g.
+/// `{daily:short} steps — {goal_pct}%`. `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(TemplateError::Unbalanced),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => return Err(TemplateError::Unbalanced),
+                            Some(c) => placeholder.push(c),
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(variable(&placeholder)?);
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            segments,
+        })
+    }
+
+    /// The text the template was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn render(&self, variables: &Variables) -> String {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Variable { name, filter } => {
+                    if let Some(value) = variables.get(name) {
+                        rendered.push_str(&apply(value, filter.as_deref()));
+                    }
+                }
+            }
+        }
+        rendered
+    }
+}
+
+// `name` or `name:filter`, checked against the known ones
+fn variable(placeholder: &str) -> Result<Segment, TemplateError> {
+    let (name, filter) = match placeholder.split_once(':') {
+        Some((name, filter)) => (name.trim(), Some(filter.trim())),
+        None => (placeholder.trim(), None),
+    };
+    if !VARIABLES.iter().any(|(known, _)| *known == name) {
+        return Err(TemplateError::UnknownVariable(name.to_string()));
+    }
+    if let Some(filter) = filter {
+        if !FILTERS.iter().any(|(known, _)| *known == filter) {
+            return Err(TemplateError::UnknownFilter(filter.to_string()));
+        }
+    }
+    Ok(Segment::Variable {
+        name: name.to_string(),
+        filter: filter.map(str::to_string),
+    })
+}
+
+// Number filters leave text alone, and numbers keep their default format
+// under text filters
+fn apply(value: &Value, filter: Option<&str>) -> String {
+    match (value, filter) {
+        (Value::Text(text), Some("upper")) => text.to_uppercase(),
+        (Value::Text(text), Some("lower")) => text.to_lowercase(),
+        (Value::Text(text), _) => text.clone(),
+        (Value::Amount(n, unit), Some("unit")) => unit.format_with_unit(*n),
+        (Value::Amount(n, _) | Value::Number(n), Some("raw")) => n.to_string(),
+        (Value::Amount(n, _) | Value::Number(n), Some("compact")) => format_number(*n),
+        (Value::Amount(n, _) | Value::Number(n), Some("short")) => format_short(*n),
+        (Value::Amount(n, _) | Value::Number(n), Some("comma")) => format_thousands(*n),
+        (Value::Amount(n, _) | Value::Number(n), Some("volume")) => format_volume(*n),
+        (Value::Amount(n, _) | Value::Number(n), Some("duration")) => format_sleep_minutes(*n),
+        (Value::Amount(n, unit), _) => unit.format(*n),
+        (Value::Number(n), _) => n.to_string(),
+    }
+}
+
+fn format_thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if n < 0 {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+/// Templates overriding a metric's built-in texts. Unset ones keep the
+/// built-in text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Templates {
+    pub details: Option<Template>,
+    pub state: Option<Template>,
+    pub hover_text: Option<Template>,
+    pub obs: Option<Template>,
+}
@@ -0,0 +1,150 @@
+mod support;
+
+use chrono::{DateTime, Local};
+use discord_rpc::achievements::{describe, Milestone, Period};
+use discord_rpc::metrics::Metric;
+use discord_rpc::presence::DryRunPresence;
+use discord_rpc::rotation::Rotation;
+use discord_rpc::Achievements;
+use serde_json::{json, Value};
+use std::time::Duration;
+use support::{at, config_problems, offline_api, write_config, FixedMetric, TempDir};
+
+// Steps fetched at `fetched_at` with these totals
+fn steps(daily: i64, yearly: i64, fetched_at: DateTime<Local>) -> FixedMetric {
+    let mut metric = FixedMetric::new("steps", 1, "", "").with_totals(daily, daily, yearly);
+    metric.restore(Value::Null, fetched_at).unwrap();
+    metric
+}
+
+fn titles(unlocked: &[discord_rpc::achievements::Unlock]) -> Vec<&str> {
+    unlocked.iter().map(|unlock| unlock.title.as_str()).collect()
+}
+
+#[test]
+fn the_first_data_only_sets_the_baseline() {
+    let mut achievements = Achievements::in_memory();
+    let unlocked = achievements.check(&steps(12_000, 1_200_000, at("2025-03-04", 9))).unwrap();
+    assert!(unlocked.is_empty());
+
+    // Already passed, so recorded without being announced
+    assert!(titles(achievements.unlocked()).contains(&"1M steps this year"));
+    assert!(titles(achievements.unlocked()).contains(&"10K steps in a day"));
+    assert_eq!(achievements.personal_best("steps").unwrap().value, 12_000);
+}
+
+#[test]
+fn milestones_unlock_once_per_period() {
+    let mut achievements = Achievements::in_memory();
+    achievements.check(&steps(5_000, 990_000, at("2025-03-04", 9))).unwrap();
+
+    let unlocked = achievements.check(&steps(15_000, 1_000_000, at("2025-03-04", 20))).unwrap();
+    assert_eq!(titles(&unlocked), vec!["10K steps in a day", "1M steps this year"]);
+    assert_eq!(unlocked[1].id, "steps.yearly.1000000");
+    assert_eq!(unlocked[1].period, "2025");
+    assert!(achievements.check(&steps(16_000, 1_001_000, at("2025-03-04", 21))).unwrap().is_empty());
+
+    // Daily milestones come back the next day, yearly ones don't
+    let unlocked = achievements.check(&steps(10_000, 1_011_000, at("2025-03-05", 21))).unwrap();
+    assert_eq!(titles(&unlocked), vec!["10K steps in a day"]);
+    assert_eq!(unlocked[0].period, "2025-03-05");
+}
+
+#[test]
+fn personal_bests_are_announced_once_a_day() {
+    let mut achievements = Achievements::in_memory().with_milestones(Vec::new());
+    achievements.check(&steps(8_000, 8_000, at("2025-03-04", 22))).unwrap();
+
+    assert!(achievements.check(&steps(9_000, 9_000, at("2025-03-04", 23))).unwrap().is_empty());
+    assert!(achievements.check(&steps(8_500, 17_500, at("2025-03-05", 20))).unwrap().is_empty());
+
+    let unlocked = achievements.check(&steps(9_500, 18_500, at("2025-03-05", 21))).unwrap();
+    assert_eq!(titles(&unlocked), vec!["New best day: 9500"]);
+    assert_eq!(unlocked[0].id, "steps.best");
+
+    // The rest of the day only raises the record
+    assert!(achievements.check(&steps(9_800, 18_800, at("2025-03-05", 22))).unwrap().is_empty());
+    let best = achievements.personal_best("steps").unwrap();
+    assert_eq!((best.value, best.date), (9_800, "2025-03-05".parse().unwrap()));
+}
+
+#[test]
+fn achievements_persist_on_disk() {
+    let dir = TempDir::new("achievements");
+    let path = dir.path().join("nested").join("achievements.json");
+
+    let mut achievements = Achievements::open(&path).unwrap();
+    achievements.check(&steps(5_000, 990_000, at("2025-03-04", 9))).unwrap();
+    achievements.check(&steps(5_000, 1_000_000, at("2025-03-04", 10))).unwrap();
+
+    let mut reopened = Achievements::open(&path).unwrap();
+    assert_eq!(titles(reopened.unlocked()), vec!["1M steps this year"]);
+    // Not a first sight any more, so new milestones are announced
+    let unlocked = reopened.check(&steps(10_000, 1_010_000, at("2025-03-05", 11))).unwrap();
+    assert_eq!(titles(&unlocked), vec!["10K steps in a day", "New best day: 10000"]);
+}
+
+#[test]
+fn rotation_shows_unlocked_achievements_first() {
+    let mut achievements = Achievements::in_memory()
+        .with_milestones(vec![Milestone::new("steps", Period::Daily, 10_000, "10K steps in a day")])
+        .with_duration(Duration::from_secs(5))
+        .with_image_key("trophy");
+    achievements.check(&steps(100, 100, Local::now())).unwrap();
+
+    let metric = FixedMetric::new("steps", 1, "Today: 10.20K", "").with_totals(10_200, 10_200, 10_200);
+    let mut rotation = Rotation::new(vec![metric.boxed()]).with_achievements(achievements);
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+
+    assert_eq!(rotation.step(&offline_api(), &mut presence).unwrap(), Duration::from_secs(5));
+    assert_eq!(rotation.step(&offline_api(), &mut presence).unwrap(), Duration::from_secs(60));
+
+    let output = String::from_utf8(presence.into_inner()).unwrap();
+    let shown: Vec<Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(shown.len(), 2);
+    assert_eq!(
+        shown[0]["activity"],
+        json!({
+            "details": "🏆 Achievement unlocked!",
+            "state": "10K steps in a day",
+            "assets": { "large_image": "trophy", "large_text": "10K steps in a day" }
+        })
+    );
+    assert_eq!(shown[1]["activity"]["details"], "Today: 10.20K");
+}
+
+#[test]
+fn milestones_are_read_from_the_config_file() {
+    let config = write_config(
+        r#"
+        [achievements]
+        default_milestones = false
+        milestones = [
+            { metric = "steps", period = "daily", value = "15K" },
+            { metric = "water", period = "yearly", value = "500 L", title = "Half a tonne of water" },
+        ]
+        "#,
+    );
+    assert_eq!(config_problems(&config), Vec::<String>::new());
+    assert_eq!(
+        config.achievements.milestones().unwrap(),
+        vec![
+            Milestone::new("steps", Period::Daily, 15_000, "15K steps in a day"),
+            Milestone::new("water", Period::Yearly, 500_000, "Half a tonne of water"),
+        ]
+    );
+    assert_eq!(describe("sleep", Period::Monthly, 12_000), "200h of sleep this month");
+
+    let config = write_config(
+        r#"
+        [[achievements.milestones]]
+        metric = "calories"
+        period = "daily"
+        value = 500
+        "#,
+    );
+    assert_eq!(
+        config_problems(&config),
+        vec!["[achievements] milestone 1: unknown metric 'calories' (expected steps, water or sleep)".to_string()]
+    );
+}
@@ -0,0 +1,156 @@
+use discord_rpc::mock::{MockApi, MockResponse};
+use discord_rpc::retry::{CircuitBreaker, RetryPolicy};
+use discord_rpc::{ApiClient, ApiError, AuthMode, LoginRequest, StepsRequest};
+use serde_json::json;
+use std::time::Duration;
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+    }
+}
+
+fn client(mock: &MockApi, token: &str) -> ApiClient {
+    ApiClient::new(mock.url(), token).unwrap().with_retry_policy(fast_retries(3))
+}
+
+#[test]
+fn serves_sample_summaries() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let api = client(&mock, "");
+
+    let steps = api.steps_summary().unwrap();
+    assert_eq!((steps.daily, steps.monthly, steps.yearly), (8421, 200_000, 1_500_000));
+    assert_eq!(api.water_summary().unwrap().daily_display, "1.5 L");
+    assert_eq!(api.sleep_summary("2026-10-17").unwrap().daily_minutes, 450);
+
+    let sleep = &mock.requests()[2];
+    assert_eq!(sleep.path, "/api/sleep/summary");
+    assert_eq!(sleep.query.as_deref(), Some("date=2026-10-17"));
+}
+
+#[test]
+fn token_is_checked_in_header_and_query() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.require_token(Some("secret"));
+
+    let token = client(&mock, "").login(&LoginRequest {
+        username: "walker".to_string(),
+        password: "hunter2".to_string(),
+    });
+    assert_eq!(token.unwrap().token, "secret");
+
+    assert!(client(&mock, "secret").steps_summary().is_ok());
+    assert!(client(&mock, "secret").with_auth_mode(AuthMode::Query).steps_summary().is_ok());
+    assert_eq!(mock.requests()[1].authorization.as_deref(), Some("Bearer secret"));
+
+    match client(&mock, "wrong").steps_summary() {
+        Err(ApiError::Unauthorized { message }) => assert_eq!(message, "Invalid token"),
+        other => panic!("expected 401, got {:?}", other),
+    }
+    // A refused token is not worth retrying
+    assert_eq!(mock.request_count("/api/steps/summary"), 3);
+}
+
+#[test]
+fn transient_errors_are_retried() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.push_responses(
+        "/api/steps/summary",
+        [MockResponse::error(503, "maintenance"), MockResponse::error(429, "slow down")],
+    );
+
+    assert_eq!(client(&mock, "").steps_summary().unwrap().daily, 8421);
+    assert_eq!(mock.request_count("/api/steps/summary"), 3);
+}
+
+#[test]
+fn additions_are_not_retried_after_a_timeout() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    // The mock takes the request in, then answers too late
+    let answer = MockResponse::ok(json!({ "steps": 2500, "date": null }));
+    mock.set_response("/api/steps/add", answer.with_delay(Duration::from_millis(500)));
+    let api = client(&mock, "").with_timeout(Duration::from_millis(100));
+
+    let request = StepsRequest { steps: 2500, date: None };
+    let result = api.add_steps(&request);
+    assert!(matches!(result, Err(ApiError::Request(_))), "{:?}", result);
+    assert_eq!(mock.request_count("/api/steps/add"), 1);
+
+    // Replacing the count is safe to repeat
+    mock.set_response("/api/steps", MockResponse::error(503, "maintenance"));
+    assert!(api.set_steps(&request).is_err());
+    assert_eq!(mock.request_count("/api/steps"), 3);
+}
+
+#[test]
+fn client_errors_are_not_retried() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.set_response("/api/water/summary", MockResponse::error(400, "bad date"));
+
+    match client(&mock, "").water_summary() {
+        Err(ApiError::Status { status, message }) => {
+            assert_eq!(status.as_u16(), 400);
+            assert_eq!(message, "bad date");
+        }
+        other => panic!("expected HTTP 400, got {:?}", other),
+    }
+    assert_eq!(mock.request_count("/api/water/summary"), 1);
+}
+
+#[test]
+fn malformed_bodies_are_decode_errors() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.set_response("/api/steps/summary", MockResponse::ok(json!({ "daily": "lots" })));
+
+    assert!(matches!(client(&mock, "").steps_summary(), Err(ApiError::Decode(_))));
+}
+
+#[test]
+fn circuit_opens_after_repeated_failures() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.set_failure_rate(1.0);
+    let api = ApiClient::new(mock.url(), "")
+        .unwrap()
+        .with_retry_policy(RetryPolicy::none())
+        .with_circuit_breaker(CircuitBreaker::new(2, Duration::from_millis(200), Duration::from_secs(1)));
+
+    assert!(api.steps_summary().unwrap_err().is_transient());
+    assert!(api.steps_summary().unwrap_err().is_transient());
+    assert!(matches!(api.steps_summary(), Err(ApiError::CircuitOpen { .. })));
+    assert_eq!(mock.requests().len(), 2);
+
+    // After the cooldown a single probe closes the circuit again
+    mock.set_failure_rate(0.0);
+    std::thread::sleep(Duration::from_millis(250));
+    assert!(api.steps_summary().is_ok());
+}
+
+#[test]
+fn scripts_queue_and_replace_responses() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.load_script(
+        r#"
+        [[response]]
+        path = "/api/steps/summary"
+        status = 502
+        times = 2
+
+        [[response]]
+        path = "/api/steps/summary"
+        body = { daily = 1, monthly = 2, yearly = 3 }
+        delay_ms = 50
+        "#,
+    )
+    .unwrap();
+    let api = client(&mock, "").with_retry_policy(RetryPolicy::none());
+
+    assert!(matches!(api.steps_summary(), Err(ApiError::Status { .. })));
+    assert!(matches!(api.steps_summary(), Err(ApiError::Status { .. })));
+    let started = std::time::Instant::now();
+    assert_eq!(api.steps_summary().unwrap().yearly, 3);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(api.steps_summary().unwrap().daily, 1);
+}
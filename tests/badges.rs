@@ -0,0 +1,123 @@
+mod support;
+
+use discord_rpc::metrics::{Metric, MetricOptions, StepsMetric};
+use discord_rpc::presence::{Activity, Badge, Badges};
+use discord_rpc::template::Template;
+use discord_rpc::Streak;
+use serde_json::json;
+use support::{config_problems, steps_metric, steps_options, write_config};
+
+fn badge(image_key: &str, text: &str) -> Option<Badge> {
+    Some(Badge {
+        image_key: image_key.to_string(),
+        text: Some(Template::parse(text).unwrap()),
+    })
+}
+
+fn badges() -> Badges {
+    Badges {
+        stale: badge("warning", "Last synced at {as_of}"),
+        goal_met: badge("check", "Goal met: {goal_pct}%"),
+        streak: badge("fire", "{streak}-day streak"),
+        default: None,
+    }
+}
+
+fn steps(daily: i64, badges: Badges) -> StepsMetric {
+    steps_metric(
+        daily,
+        MetricOptions {
+            goal: Some(10_000),
+            badges,
+            ..steps_options()
+        },
+    )
+}
+
+fn small_image(activity: &Activity) -> (Option<&str>, Option<&str>) {
+    let assets = activity.assets.as_ref().unwrap();
+    (assets.small_image.as_deref(), assets.small_text.as_deref())
+}
+
+#[test]
+fn badges_follow_the_metric_state() {
+    let mut metric = steps(8421, badges());
+    assert_eq!(small_image(&Activity::for_metric(&metric)), (None, None));
+
+    metric.set_streak(Some(Streak { current: 3, longest: 5 }));
+    assert_eq!(small_image(&Activity::for_metric(&metric)), (Some("fire"), Some("3-day streak")));
+
+    let metric = steps(12_000, badges());
+    assert_eq!(small_image(&Activity::for_metric(&metric)), (Some("check"), Some("Goal met: 120%")));
+
+    // Stale data wins over everything else
+    let as_of = metric.fetched_at().unwrap().format("%H:%M").to_string();
+    let activity = Activity::stale(&metric);
+    assert_eq!(small_image(&activity), (Some("warning"), Some(format!("Last synced at {}", as_of).as_str())));
+}
+
+#[test]
+fn the_default_badge_is_shown_otherwise() {
+    let badges = Badges {
+        default: Some(Badge {
+            image_key: "shoe".to_string(),
+            text: None,
+        }),
+        ..Badges::default()
+    };
+    let metric = steps(12_000, badges);
+    let activity = serde_json::to_value(Activity::stale(&metric)).unwrap();
+    assert_eq!(
+        activity["assets"],
+        json!({ "large_image": "walking", "large_text": "I'm walking here!", "small_image": "shoe" })
+    );
+
+    // Without badges the payload stays as it was
+    let activity = serde_json::to_value(Activity::for_metric(&steps(12_000, Badges::default()))).unwrap();
+    assert_eq!(activity["assets"], json!({ "large_image": "walking", "large_text": "I'm walking here!" }));
+}
+
+#[test]
+fn empty_hover_text_is_left_out_of_the_assets() {
+    let options = MetricOptions {
+        hover_text: String::new(),
+        ..steps_options()
+    };
+    let activity = serde_json::to_value(Activity::for_metric(&steps_metric(8421, options))).unwrap();
+    assert_eq!(activity["assets"], json!({ "large_image": "walking" }));
+}
+
+#[test]
+fn badges_are_read_and_validated_from_the_config_file() {
+    let config = write_config(
+        r#"
+        [steps.badges]
+        goal_met = { image_key = "check", text = "Goal met!" }
+        stale = { image_key = "warning" }
+
+        [water.badges]
+        streak = { text = "{streak} days" }
+        default = { image_key = "drop", text = "{dialy}" }
+
+        [sleep]
+        enabled = true
+
+        [sleep.badges]
+        default = { image_key = "moon", text = "{daily} of sleep" }
+        "#,
+    );
+    assert_eq!(
+        config_problems(&config),
+        vec![
+            "[water.badges] streak: image_key is required".to_string(),
+            "[water.badges] default: text: unknown variable 'dialy'".to_string(),
+        ]
+    );
+
+    let metrics = config.enabled_metrics();
+    let steps = &metrics[0].options().badges;
+    assert_eq!(steps.goal_met, badge("check", "Goal met!"));
+    assert_eq!(steps.stale.as_ref().unwrap().text, None);
+    assert_eq!(metrics[1].options().badges, Badges::default());
+    assert_eq!(metrics[2].options().badges.default, badge("moon", "{daily} of sleep"));
+}
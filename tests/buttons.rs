@@ -0,0 +1,127 @@
+mod support;
+
+use discord_rpc::metrics::{MetricOptions, StepsMetric};
+use discord_rpc::presence::{Activity, Button, ButtonError};
+use discord_rpc::Config;
+use serde_json::json;
+use std::env;
+use support::{config_file, config_problems, steps_metric, steps_options, write_config, TempDir};
+
+fn steps(buttons: Vec<Button>) -> StepsMetric {
+    steps_metric(8421, MetricOptions { buttons, ..steps_options() })
+}
+
+#[test]
+fn buttons_follow_discord_limits() {
+    let button = Button::new(" View my stats ", "https://example.com/u/me").unwrap();
+    assert_eq!(button.label, "View my stats");
+
+    let label = "x".repeat(33);
+    assert_eq!(Button::new(&label, "https://example.com"), Err(ButtonError::LabelTooLong(33)));
+    assert!(Button::new(&"é".repeat(32), "https://example.com").is_ok());
+    assert_eq!(Button::new("", "https://example.com"), Err(ButtonError::EmptyLabel));
+
+    let url = format!("https://example.com/{}", "a".repeat(500));
+    assert_eq!(Button::new("Stats", &url), Err(ButtonError::UrlTooLong(520)));
+    for url in ["example.com", "ftp://example.com", "https://", "https://exa mple.com"] {
+        assert_eq!(Button::new("Stats", url), Err(ButtonError::InvalidUrl(url.to_string())));
+    }
+}
+
+#[test]
+fn buttons_are_sent_with_the_activity() {
+    let buttons = vec![
+        Button::new("View my stats", "https://example.com/u/me").unwrap(),
+        Button::new("Join challenge", "https://example.com/join").unwrap(),
+    ];
+    let activity = serde_json::to_value(Activity::for_metric(&steps(buttons))).unwrap();
+    assert_eq!(
+        activity["buttons"],
+        json!([
+            { "label": "View my stats", "url": "https://example.com/u/me" },
+            { "label": "Join challenge", "url": "https://example.com/join" },
+        ])
+    );
+    assert_eq!(Activity::stale(&steps(Vec::new())).buttons, Vec::new());
+
+    // Without buttons the payload has no buttons field at all
+    let activity = serde_json::to_value(Activity::for_metric(&steps(Vec::new()))).unwrap();
+    assert!(activity.get("buttons").is_none());
+}
+
+#[test]
+fn buttons_are_read_and_validated_from_the_config_file() {
+    let config = write_config(
+        r#"
+        [steps]
+        buttons = [
+            { label = "View my stats", url = "https://example.com/u/me" },
+            { label = "Join this month's steps challenge", url = "https://example.com/join" },
+        ]
+
+        [[water.buttons]]
+        label = "One"
+        url = "https://example.com/1"
+
+        [[water.buttons]]
+        label = "Two"
+        url = "https://example.com/2"
+
+        [[water.buttons]]
+        label = "Three"
+        url = "example.com/3"
+        "#,
+    );
+    assert_eq!(
+        config_problems(&config),
+        vec![
+            "[steps] button 2: label is 33 characters long (at most 32 are allowed)".to_string(),
+            "[water] buttons: Discord shows at most 2, got 3".to_string(),
+            "[water] button 3: url 'example.com/3' must be an http:// or https:// link".to_string(),
+        ]
+    );
+
+    let metrics = config.enabled_metrics();
+    assert_eq!(
+        metrics[0].options().buttons,
+        vec![Button::new("View my stats", "https://example.com/u/me").unwrap()]
+    );
+    assert_eq!(metrics[1].options().buttons.len(), 2);
+}
+
+#[test]
+fn button_overrides_from_the_environment_must_not_leave_gaps() {
+    let dir = TempDir::new("buttons-env");
+    let path = config_file(
+        &dir,
+        r#"
+        [[steps.buttons]]
+        label = "View my stats"
+        url = "https://example.com/u/me"
+        "#,
+    );
+
+    // Only this test touches these variables
+    env::set_var("STEPS_BUTTON_2_LABEL", "Join challenge");
+    env::set_var("STEPS_BUTTON_2_URL", "https://example.com/join");
+    env::set_var("WATER_BUTTON_2_LABEL", "Refill");
+    let config = Config::load(Some(&path)).unwrap();
+    for name in ["STEPS_BUTTON_2_LABEL", "STEPS_BUTTON_2_URL", "WATER_BUTTON_2_LABEL"] {
+        env::remove_var(name);
+    }
+
+    assert_eq!(
+        config_problems(&config),
+        vec!["WATER_BUTTON_2_*: set without WATER_BUTTON_1_LABEL and WATER_BUTTON_1_URL".to_string()]
+    );
+
+    let metrics = config.enabled_metrics();
+    assert_eq!(
+        metrics[0].options().buttons,
+        vec![
+            Button::new("View my stats", "https://example.com/u/me").unwrap(),
+            Button::new("Join challenge", "https://example.com/join").unwrap(),
+        ]
+    );
+    assert_eq!(metrics[1].options().buttons, Vec::new());
+}
@@ -0,0 +1,53 @@
+mod support;
+
+use discord_rpc::cache::Cache;
+use discord_rpc::mock::MockApi;
+use discord_rpc::presence::DryRunPresence;
+use discord_rpc::retry::RetryPolicy;
+use discord_rpc::rotation::Rotation;
+use discord_rpc::{ApiClient, Config};
+use support::TempDir;
+
+#[test]
+fn rotation_caches_fetched_data_in_its_directory() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let api = ApiClient::new(mock.url(), "").unwrap().with_retry_policy(RetryPolicy::none());
+    let dir = TempDir::new("cache");
+    let cache = Cache::new(dir.path().join("nested"));
+
+    let mut metrics = Config::default().enabled_metrics();
+    metrics.truncate(1);
+    let mut rotation = Rotation::new(metrics).with_cache(cache.clone());
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    rotation.step(&api, &mut presence).unwrap();
+
+    let entry = cache.load("steps").unwrap().unwrap();
+    assert_eq!(entry.data["daily"], 8421);
+    assert!(cache.load("water").unwrap().is_none());
+
+    // A fresh metric picks up where the last run left off
+    let mut metric = Config::default().enabled_metrics().remove(0);
+    assert_eq!(cache.restore(metric.as_mut()).unwrap(), Some(entry.fetched_at));
+    assert_eq!(metric.totals().unwrap().daily, 8421);
+
+    cache.clear().unwrap();
+    assert!(cache.load("steps").unwrap().is_none());
+    assert!(!cache.dir().exists());
+}
+
+#[test]
+fn rotation_without_a_cache_writes_nothing() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let api = ApiClient::new(mock.url(), "").unwrap().with_retry_policy(RetryPolicy::none());
+    let dir = TempDir::new("no-cache");
+
+    let mut config = Config::default();
+    config.cache.path = Some(dir.path().join("cache"));
+    config.cache.enabled = false;
+    assert_eq!(config.cache.path(), None);
+
+    let mut rotation = Rotation::new(config.enabled_metrics());
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    rotation.step(&api, &mut presence).unwrap();
+    assert!(!dir.path().join("cache").exists());
+}
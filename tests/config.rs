@@ -0,0 +1,147 @@
+mod support;
+
+use discord_rpc::config::parse_duration;
+use discord_rpc::{Config, ConfigError};
+use std::env;
+use std::fs;
+use std::time::Duration;
+use support::{config_file, config_problems, TempDir};
+
+#[test]
+fn durations_parse_with_or_without_a_unit() {
+    assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration(" 2m "), Ok(Duration::from_secs(120)));
+    assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+
+    assert!(parse_duration("soon").is_err());
+    assert!(parse_duration("5d").is_err());
+}
+
+#[test]
+fn overlong_durations_are_rejected() {
+    assert_eq!(
+        parse_duration("999999999999999999h"),
+        Err("'999999999999999999h' is too long a duration".to_string())
+    );
+    assert!(parse_duration("18446744073709551615m").is_err());
+    // Too large for a u64 before any unit is applied
+    assert!(parse_duration("99999999999999999999").is_err());
+}
+
+#[test]
+fn every_problem_in_the_file_is_reported() {
+    let dir = TempDir::new("config-problems");
+    let path = dir.path().join("config.toml");
+    fs::write(
+        &path,
+        r#"
+        [api]
+        url = "localhost:8080"
+
+        [discord]
+        mode = "multi"
+
+        [timing]
+        refresh_interval = 0
+
+        [retry]
+        max_attempts = 0
+
+        [steps]
+        client_id = 0
+
+        [water]
+        display_duration = "0s"
+
+        [sleep]
+        enabled = false
+        "#,
+    )
+    .unwrap();
+
+    let config = Config::from_file(&path).unwrap();
+    assert_eq!(
+        config_problems(&config),
+        vec![
+            "[api] url 'localhost:8080' must start with http:// or https://".to_string(),
+            "[timing] refresh_interval must be greater than zero".to_string(),
+            "[retry] max_attempts must be at least 1".to_string(),
+            "[steps] client_id must not be 0".to_string(),
+            "[water] display_duration must be greater than zero".to_string(),
+            "[water] client_id is required while water is enabled (or set WATER_DISCORD_CLIENT_ID)".to_string(),
+        ]
+    );
+
+    // Without a file only the API URL and the Discord application are missing
+    assert_eq!(
+        config_problems(&Config::default()),
+        vec![
+            "[api] url is required (or set API_URL)".to_string(),
+            "[steps] client_id is required while steps is enabled (or set STEPS_DISCORD_CLIENT_ID)".to_string(),
+            "[water] client_id is required while water is enabled (or set WATER_DISCORD_CLIENT_ID)".to_string(),
+            "[sleep] client_id is required while sleep is enabled (or set SLEEP_DISCORD_CLIENT_ID)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn unknown_settings_are_rejected() {
+    let dir = TempDir::new("config-unknown");
+    let path = config_file(
+        &dir,
+        r#"
+        [timing]
+        refresh_intervall = 30
+        "#,
+    );
+    match Config::from_file(&path) {
+        Err(ConfigError::Parse { source, .. }) => assert!(source.to_string().contains("refresh_intervall")),
+        other => panic!("expected the typo to be rejected, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn environment_variables_win_over_the_file() {
+    let dir = TempDir::new("config-env");
+    let path = config_file(
+        &dir,
+        r#"
+        [timing]
+        refresh_interval = "30s"
+
+        [steps]
+        goal = 10000
+        "#,
+    );
+
+    // Only this test touches these variables
+    let vars = [
+        ("API_URL", "http://127.0.0.1:8080"),
+        ("DISCORD_CLIENT_ID", "42"),
+        ("REFRESH_INTERVAL", "2m"),
+        ("STEPS_GOAL", "12K"),
+        ("ENABLE_SLEEP", "false"),
+        ("RETRY_MAX_ATTEMPTS", "many"),
+    ];
+    for (name, value) in vars {
+        env::set_var(name, value);
+    }
+    let config = Config::load(Some(&path)).unwrap();
+    for (name, _) in vars {
+        env::remove_var(name);
+    }
+
+    assert_eq!(config.api_url(), Ok("http://127.0.0.1:8080"));
+    assert_eq!(config.single_client_id(), Some(42));
+    assert_eq!(config.timing.refresh_interval, Duration::from_secs(120));
+    // Bad values are reported along with the problems in the file
+    assert_eq!(
+        config_problems(&config),
+        vec!["RETRY_MAX_ATTEMPTS must be a whole number, got 'many'".to_string()]
+    );
+
+    let metrics = config.enabled_metrics();
+    assert_eq!(metrics.len(), 2);
+    assert_eq!(metrics[0].options().goal, Some(12_000));
+}
@@ -0,0 +1,125 @@
+mod support;
+
+use discord_rpc::ipc::{self, ConnectionState, IpcClient, IpcError, Opcode};
+use discord_rpc::presence::{Activity, Assets, Timestamps};
+use serde_json::json;
+use std::time::Duration;
+use support::{FakeDiscord, Record};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn activity() -> Activity {
+    Activity {
+        details: "Today: 8.42K".to_string(),
+        state: "Monthly: 200.00K | Yearly: 1.50M".to_string(),
+        timestamps: Some(Timestamps { start: 100, end: 200 }),
+        assets: Some(Assets {
+            large_image: "steps".to_string(),
+            large_text: "I'm walking here!".to_string(),
+            ..Assets::default()
+        }),
+        ..Activity::default()
+    }
+}
+
+#[test]
+fn frames_round_trip() {
+    let payload = json!({ "cmd": "SET_ACTIVITY", "nonce": "1" });
+    let mut buffer = Vec::new();
+    ipc::write_frame(&mut buffer, Opcode::Frame, &payload).unwrap();
+
+    assert_eq!(&buffer[..4], &1u32.to_le_bytes());
+    assert_eq!(buffer.len(), 8 + serde_json::to_vec(&payload).unwrap().len());
+
+    let (opcode, decoded) = ipc::read_frame(&mut buffer.as_slice()).unwrap();
+    assert_eq!(opcode, Opcode::Frame);
+    assert_eq!(decoded, payload);
+}
+
+#[test]
+fn unknown_opcodes_are_rejected() {
+    let mut frame = 9u32.to_le_bytes().to_vec();
+    frame.extend_from_slice(&2u32.to_le_bytes());
+    frame.extend_from_slice(b"{}");
+    assert!(matches!(ipc::read_frame(&mut frame.as_slice()), Err(IpcError::Protocol(_))));
+}
+
+#[test]
+fn socket_candidates_cover_every_index_and_sandbox() {
+    let candidates = ipc::socket_candidates();
+    for index in 0..10 {
+        let name = format!("discord-ipc-{}", index);
+        assert!(candidates.iter().any(|path| path.ends_with(&name)));
+        assert!(candidates.iter().any(|path| path.ends_with(format!("app/com.discordapp.Discord/{}", name))));
+        assert!(candidates.iter().any(|path| path.ends_with(format!("snap.discord/{}", name))));
+    }
+}
+
+#[test]
+fn sends_handshake_and_activity() {
+    let discord = FakeDiscord::start();
+    let mut client = IpcClient::new(42).with_socket_path(discord.socket_path());
+    assert_eq!(client.state(), ConnectionState::Disconnected);
+
+    client.connect(TIMEOUT).unwrap();
+    assert_eq!(client.state(), ConnectionState::Connected);
+
+    client.set_activity(&activity()).unwrap();
+    client.clear_activity().unwrap();
+
+    let pid = std::process::id() as u64;
+    assert_eq!(
+        discord.records(),
+        vec![
+            Record::Handshake { client_id: 42 },
+            Record::SetActivity {
+                client_id: 42,
+                pid,
+                activity: Some(json!({
+                    "details": "Today: 8.42K",
+                    "state": "Monthly: 200.00K | Yearly: 1.50M",
+                    "timestamps": { "start": 100, "end": 200 },
+                    "assets": { "large_image": "steps", "large_text": "I'm walking here!" },
+                })),
+            },
+            Record::SetActivity { client_id: 42, pid, activity: None },
+        ]
+    );
+}
+
+#[test]
+fn missing_socket_means_not_running() {
+    let discord = FakeDiscord::start();
+    let path = discord.socket_path().with_file_name("discord-ipc-9");
+    let mut client = IpcClient::new(1).with_socket_path(path);
+    assert!(matches!(client.connect(TIMEOUT), Err(IpcError::NotRunning)));
+}
+
+#[test]
+fn rejected_client_id_reports_close_code() {
+    let discord = FakeDiscord::start();
+    discord.reject(7);
+    let mut client = IpcClient::new(7).with_socket_path(discord.socket_path());
+
+    match client.connect(TIMEOUT) {
+        Err(IpcError::Closed { code, .. }) => assert_eq!(code, 4000),
+        other => panic!("expected a close frame, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(client.state(), ConnectionState::Disconnected);
+}
+
+#[test]
+fn lost_connection_is_detected_and_recovered() {
+    let discord = FakeDiscord::start();
+    let mut client = IpcClient::new(3).with_socket_path(discord.socket_path());
+    client.connect(TIMEOUT).unwrap();
+
+    discord.disconnect(3);
+    assert!(matches!(client.set_activity(&activity()), Err(IpcError::Io(_))));
+    assert_eq!(client.state(), ConnectionState::Disconnected);
+
+    client.connect(TIMEOUT).unwrap();
+    client.set_activity(&activity()).unwrap();
+    assert_eq!(discord.handshakes(), vec![3, 3]);
+    assert_eq!(discord.activities(3).len(), 1);
+}
@@ -0,0 +1,144 @@
+mod support;
+
+use chrono::Local;
+use discord_rpc::config::{parse_goal, GoalValue};
+use discord_rpc::metrics::{format_short, Metric, MetricOptions, Progress, SleepMetric, StepsMetric, WaterMetric};
+use discord_rpc::presence::Activity;
+use support::{config_problems, sleep_summary, steps_metric, steps_options, water_summary, write_config};
+
+// The goal with its own image and hover text
+fn with_goal(goal: Option<i64>) -> MetricOptions {
+    MetricOptions {
+        goal,
+        goal_image_key: Some("trophy".to_string()),
+        goal_hover_text: Some("Goal smashed!".to_string()),
+        ..steps_options()
+    }
+}
+
+fn steps(daily: i64, goal: Option<i64>) -> StepsMetric {
+    steps_metric(daily, with_goal(goal))
+}
+
+fn text(goal: &str) -> GoalValue {
+    GoalValue::Text(goal.to_string())
+}
+
+#[test]
+fn progress_reports_percent_and_remaining() {
+    let progress = Progress { value: 8421, goal: 10_000 };
+    assert_eq!(progress.percent(), 84);
+    assert_eq!(progress.remaining(), 1579);
+    assert!(!progress.is_met());
+
+    let progress = Progress { value: 12_500, goal: 10_000 };
+    assert_eq!(progress.percent(), 125);
+    assert_eq!(progress.remaining(), 0);
+    assert!(progress.is_met());
+}
+
+#[test]
+fn steps_show_progress_towards_the_goal() {
+    let metric = steps(8421, Some(10_000));
+    assert_eq!(metric.details(), "Today: 8.42K / 10K — 84%");
+    assert_eq!(metric.state(), "1.58K to go | Monthly: 200.00K | Yearly: 1.50M");
+    assert_eq!(
+        metric.obs_text(),
+        "Today: 8.42K\nMonthly: 200.00K\nYearly: 1.50M\nGoal: 10K (84%)\nRemaining: 1.58K"
+    );
+    assert_eq!(metric.image_key(), "walking");
+    assert_eq!(metric.hover_text(), "I'm walking here!");
+}
+
+#[test]
+fn met_goals_switch_image_and_hover_text() {
+    let metric = steps(10_250, Some(10_000));
+    assert_eq!(metric.details(), "Today: 10.25K / 10K — 102%");
+    assert_eq!(metric.state(), "Goal met ✅ | Monthly: 200.00K | Yearly: 1.50M");
+    assert!(metric.obs_text().ends_with("\nGoal: 10K (102%)\nGoal met!"));
+
+    let activity = Activity::for_metric(&metric);
+    let assets = activity.assets.unwrap();
+    assert_eq!(assets.large_image, "trophy");
+    assert_eq!(assets.large_text, "Goal smashed!");
+}
+
+#[test]
+fn without_a_goal_nothing_changes() {
+    let metric = steps(8421, None);
+    assert_eq!(metric.details(), "Today: 8.42K");
+    assert_eq!(metric.state(), "Monthly: 200.00K | Yearly: 1.50M");
+    assert_eq!(metric.obs_text(), "Today: 8.42K\nMonthly: 200.00K\nYearly: 1.50M");
+    assert_eq!(metric.image_key(), "walking");
+}
+
+#[test]
+fn water_and_sleep_goals_use_their_units() {
+    let mut water = WaterMetric::new(with_goal(Some(2500)));
+    water.restore(water_summary(), Local::now()).unwrap();
+    assert_eq!(water.details(), "Today: 1.5 L / 2.5 L — 60%");
+    assert_eq!(water.state(), "1 L to go | Monthly: 30 L | Yearly: 300 L");
+
+    let mut sleep = SleepMetric::new(with_goal(Some(480)));
+    sleep.restore(sleep_summary(), Local::now()).unwrap();
+    assert!(sleep.details().starts_with("Today: 7h 30m / 8h — 93% | "));
+    assert_eq!(sleep.state(), "30m to go | Monthly: 150h | Yearly: 1500h");
+    assert_eq!(sleep.obs_text(), "Today: 7h 30m\nGoal: 8h (93%)\nRemaining: 30m");
+}
+
+#[test]
+fn goals_parse_in_each_metric_unit() {
+    assert_eq!(parse_goal("steps", &GoalValue::Number(10_000.0)), Ok(10_000));
+    assert_eq!(parse_goal("steps", &text("10K")), Ok(10_000));
+    assert_eq!(parse_goal("steps", &text("12,500")), Ok(12_500));
+    assert_eq!(parse_goal("steps", &text("1.5m")), Ok(1_500_000));
+
+    assert_eq!(parse_goal("water", &GoalValue::Number(2500.0)), Ok(2500));
+    assert_eq!(parse_goal("water", &text("2.5 L")), Ok(2500));
+    assert_eq!(parse_goal("water", &text("750ml")), Ok(750));
+
+    assert_eq!(parse_goal("sleep", &GoalValue::Number(480.0)), Ok(480));
+    assert_eq!(parse_goal("sleep", &text("8h")), Ok(480));
+    assert_eq!(parse_goal("sleep", &text("7h 30m")), Ok(450));
+    assert_eq!(parse_goal("sleep", &text("7.5h")), Ok(450));
+    assert_eq!(parse_goal("sleep", &text("450 min")), Ok(450));
+
+    assert!(parse_goal("steps", &text("lots")).is_err());
+    assert!(parse_goal("water", &text("2 gallons")).is_err());
+    assert!(parse_goal("sleep", &GoalValue::Number(0.0)).is_err());
+}
+
+#[test]
+fn goals_are_read_and_validated_from_the_config_file() {
+    let config = write_config(
+        r#"
+        [steps]
+        goal = 10000
+        goal_image_key = "trophy"
+
+        [water]
+        goal = "2.5 L"
+
+        [sleep]
+        goal = "forever"
+        "#,
+    );
+    assert_eq!(
+        config_problems(&config),
+        vec!["[sleep] goal: 'forever' is not a valid sleep goal".to_string()]
+    );
+
+    let metrics = config.enabled_metrics();
+    assert_eq!(metrics[0].options().goal, Some(10_000));
+    assert_eq!(metrics[0].options().goal_image_key.as_deref(), Some("trophy"));
+    assert_eq!(metrics[1].options().goal, Some(2500));
+    assert_eq!(metrics[2].options().goal, None);
+}
+
+#[test]
+fn short_numbers_drop_trailing_zeros() {
+    assert_eq!(format_short(950), "950");
+    assert_eq!(format_short(10_000), "10K");
+    assert_eq!(format_short(8_400), "8.4K");
+    assert_eq!(format_short(1_500_000), "1.5M");
+}
@@ -0,0 +1,104 @@
+mod support;
+
+use chrono::Local;
+use discord_rpc::presence::DryRunPresence;
+use discord_rpc::rotation::Rotation;
+use discord_rpc::{History, Totals};
+use support::{at, offline_api, FixedMetric, TempDir};
+
+fn totals(daily: i64) -> Totals {
+    Totals {
+        daily,
+        monthly: daily * 10,
+        yearly: daily * 100,
+    }
+}
+
+#[test]
+fn only_distinct_values_are_appended() {
+    let history = History::open_in_memory().unwrap();
+
+    assert!(history.append("steps", at("2025-03-04", 9), totals(100)).unwrap());
+    assert!(!history.append("steps", at("2025-03-04", 10), totals(100)).unwrap());
+    assert!(history.append("steps", at("2025-03-04", 11), totals(250)).unwrap());
+    // The same value on another day is new data
+    assert!(history.append("steps", at("2025-03-05", 9), totals(250)).unwrap());
+    assert!(history.append("water", at("2025-03-04", 9), totals(100)).unwrap());
+
+    let day = "2025-03-04".parse().unwrap();
+    let entries = history.entries("steps", day).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].fetched_at, at("2025-03-04", 9));
+    assert_eq!(entries[1].totals, totals(250));
+
+    let last = history.on("steps", day).unwrap().unwrap();
+    assert_eq!(last.metric, "steps");
+    assert_eq!(last.date, day);
+    assert_eq!(last.totals, totals(250));
+    assert!(history.on("sleep", day).unwrap().is_none());
+}
+
+#[test]
+fn days_returns_the_last_value_of_each_day() {
+    let history = History::open_in_memory().unwrap();
+    history.append("steps", at("2025-03-01", 20), totals(5000)).unwrap();
+    history.append("steps", at("2025-03-03", 9), totals(100)).unwrap();
+    history.append("steps", at("2025-03-03", 22), totals(9000)).unwrap();
+    history.append("steps", at("2025-03-08", 22), totals(1)).unwrap();
+
+    let days = history
+        .days("steps", "2025-03-01".parse().unwrap(), "2025-03-07".parse().unwrap())
+        .unwrap();
+    let daily: Vec<(String, i64)> = days
+        .iter()
+        .map(|entry| (entry.date.to_string(), entry.totals.daily))
+        .collect();
+    assert_eq!(daily, vec![("2025-03-01".to_string(), 5000), ("2025-03-03".to_string(), 9000)]);
+}
+
+#[test]
+fn metrics_without_data_are_not_recorded() {
+    let history = History::open_in_memory().unwrap();
+    let metric = FixedMetric::new("steps", 1, "Today: 1", "").with_totals(1, 2, 3);
+    // Never fetched
+    assert!(!history.record(&metric).unwrap());
+}
+
+#[test]
+fn rotation_records_each_fetch() {
+    let mut rotation = Rotation::new(vec![FixedMetric::new("steps", 1, "Today: 8.42K", "")
+        .with_totals(8421, 200_000, 1_500_000)
+        .boxed()])
+    .with_history(History::open_in_memory().unwrap());
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+
+    rotation.step(&offline_api(), &mut presence).unwrap();
+    rotation.step(&offline_api(), &mut presence).unwrap();
+
+    let today = Local::now().date_naive();
+    let entries = rotation.history().unwrap().entries("steps", today).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].totals,
+        Totals {
+            daily: 8421,
+            monthly: 200_000,
+            yearly: 1_500_000
+        }
+    );
+}
+
+#[test]
+fn history_persists_on_disk() {
+    let dir = TempDir::new("history");
+    let path = dir.path().join("nested").join("history.db");
+
+    History::open(&path)
+        .unwrap()
+        .append("sleep", at("2025-01-02", 8), totals(420))
+        .unwrap();
+
+    let reopened = History::open(&path).unwrap();
+    let entry = reopened.on("sleep", "2025-01-02".parse().unwrap()).unwrap().unwrap();
+    assert_eq!(entry.totals.daily, 420);
+}
@@ -0,0 +1,175 @@
+mod support;
+
+use discord_rpc::presence::{day_timestamps, DiscordPresence, DryRunPresence};
+use discord_rpc::rotation::Rotation;
+use serde_json::{json, Value};
+use std::time::Duration;
+use support::{offline_api, FakeDiscord, FixedMetric, Record};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn rotation() -> Rotation {
+    Rotation::new(vec![
+        FixedMetric::new("steps", 1, "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M").boxed(),
+        FixedMetric::new("water", 2, "Today: 1.5 L", "Monthly: 30 L | Yearly: 300 L").boxed(),
+    ])
+}
+
+fn expected(id: &str, details: &str, state: &str) -> Value {
+    let day = day_timestamps();
+    json!({
+        "details": details,
+        "state": state,
+        "timestamps": { "start": day.start, "end": day.end },
+        "assets": { "large_image": format!("{}_image", id), "large_text": format!("{} hover", id) },
+    })
+}
+
+#[test]
+fn multi_mode_shows_each_metric_through_its_application() {
+    let discord = FakeDiscord::start();
+    let mut rotation = rotation();
+    let mut presence = DiscordPresence::new(rotation.metrics(), TIMEOUT).with_socket_path(discord.socket_path());
+    presence.connect();
+    let api = offline_api();
+    let pid = std::process::id() as u64;
+
+    assert_eq!(rotation.step(&api, &mut presence).unwrap(), Duration::from_secs(60));
+    rotation.step(&api, &mut presence).unwrap();
+
+    assert_eq!(
+        discord.records(),
+        vec![
+            Record::Handshake { client_id: 1 },
+            Record::Handshake { client_id: 2 },
+            Record::SetActivity {
+                client_id: 1,
+                pid,
+                activity: Some(expected("steps", "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M")),
+            },
+            Record::SetActivity { client_id: 2, pid, activity: None },
+            Record::SetActivity {
+                client_id: 2,
+                pid,
+                activity: Some(expected("water", "Today: 1.5 L", "Monthly: 30 L | Yearly: 300 L")),
+            },
+            Record::SetActivity { client_id: 1, pid, activity: None },
+        ]
+    );
+}
+
+#[test]
+fn single_mode_rotates_one_application() {
+    let discord = FakeDiscord::start();
+    let mut rotation = rotation();
+    let mut presence = DiscordPresence::new_single(99, TIMEOUT).with_socket_path(discord.socket_path());
+    presence.connect();
+    let api = offline_api();
+
+    for _ in 0..3 {
+        rotation.step(&api, &mut presence).unwrap();
+    }
+
+    assert_eq!(discord.handshakes(), vec![99]);
+    assert_eq!(
+        discord.activities(99),
+        vec![
+            expected("steps", "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M"),
+            expected("water", "Today: 1.5 L", "Monthly: 30 L | Yearly: 300 L"),
+            expected("steps", "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M"),
+        ]
+    );
+    assert!(discord.records().iter().all(|record| !matches!(record, Record::SetActivity { activity: None, .. })));
+}
+
+#[test]
+fn only_the_disconnected_client_reconnects() {
+    let discord = FakeDiscord::start();
+    let mut rotation = rotation();
+    let mut presence = DiscordPresence::new(rotation.metrics(), TIMEOUT).with_socket_path(discord.socket_path());
+    presence.connect();
+    let api = offline_api();
+    rotation.step(&api, &mut presence).unwrap();
+    rotation.step(&api, &mut presence).unwrap();
+
+    discord.disconnect(2);
+    discord.clear_records();
+    // Steps still updates; clearing water notices the lost connection
+    rotation.step(&api, &mut presence).unwrap();
+    // Water reconnects on its own turn
+    rotation.step(&api, &mut presence).unwrap();
+
+    assert_eq!(discord.handshakes(), vec![2]);
+    assert_eq!(discord.activities(1).len(), 1);
+    assert_eq!(
+        discord.activities(2),
+        vec![expected("water", "Today: 1.5 L", "Monthly: 30 L | Yearly: 300 L")]
+    );
+}
+
+#[test]
+fn survives_discord_restarting() {
+    let discord = FakeDiscord::start();
+    let mut rotation = rotation();
+    let mut presence = DiscordPresence::new(rotation.metrics(), TIMEOUT).with_socket_path(discord.socket_path());
+    presence.connect();
+    let api = offline_api();
+    rotation.step(&api, &mut presence).unwrap();
+
+    discord.disconnect_all();
+    discord.clear_records();
+    // The first update after the restart reconnects and is sent again
+    rotation.step(&api, &mut presence).unwrap();
+    assert_eq!(discord.handshakes(), vec![2]);
+    assert_eq!(
+        discord.activities(2),
+        vec![expected("water", "Today: 1.5 L", "Monthly: 30 L | Yearly: 300 L")]
+    );
+
+    rotation.step(&api, &mut presence).unwrap();
+    let mut handshakes = discord.handshakes();
+    handshakes.sort();
+    assert_eq!(handshakes, vec![1, 2]);
+    assert_eq!(
+        discord.activities(1),
+        vec![expected("steps", "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M")]
+    );
+}
+
+#[test]
+fn other_slots_are_cleared_when_a_client_cannot_connect() {
+    let discord = FakeDiscord::start();
+    discord.reject(2);
+    let mut rotation = rotation();
+    let mut presence = DiscordPresence::new(rotation.metrics(), TIMEOUT).with_socket_path(discord.socket_path());
+    presence.connect();
+    let api = offline_api();
+    rotation.step(&api, &mut presence).unwrap();
+
+    discord.clear_records();
+    // Water still can't connect, but steps no longer shows either
+    rotation.step(&api, &mut presence).unwrap();
+
+    assert_eq!(discord.handshakes(), vec![2]);
+    assert!(discord.activities(2).is_empty());
+    let pid = std::process::id() as u64;
+    assert!(discord.records().contains(&Record::SetActivity { client_id: 1, pid, activity: None }));
+}
+
+#[test]
+fn dry_run_renders_the_same_activity() {
+    let mut rotation = rotation();
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    rotation.step(&offline_api(), &mut presence).unwrap();
+
+    let output = String::from_utf8(presence.into_inner()).unwrap();
+    let record: Value = serde_json::from_str(output.trim()).unwrap();
+    assert_eq!(
+        record,
+        json!({
+            "metric": "steps",
+            "client_id": 1,
+            "activity": expected("steps", "Today: 8.42K", "Monthly: 200.00K | Yearly: 1.50M"),
+        })
+    );
+}
@@ -0,0 +1,288 @@
+mod support;
+
+use chrono::Local;
+use discord_rpc::metrics::format_volume;
+use discord_rpc::server::Server;
+use discord_rpc::store::{Series, Store, StoreError};
+use discord_rpc::*;
+use std::thread;
+use std::time::Instant;
+use support::{date, TempDir};
+
+fn start() -> Server {
+    Server::start("127.0.0.1:0", Store::open_in_memory().unwrap()).unwrap()
+}
+
+fn client(server: &Server, token: &str) -> ApiClient {
+    ApiClient::new(server.url(), token)
+        .unwrap()
+        .with_retry_policy(retry::RetryPolicy::none())
+}
+
+fn register(server: &Server, username: &str) -> ApiClient {
+    let response = client(server, "")
+        .register(&RegisterRequest {
+            username: username.to_string(),
+            password: "correct horse".to_string(),
+        })
+        .unwrap();
+    client(server, &response.token)
+}
+
+fn status(error: ApiError) -> u16 {
+    match error {
+        ApiError::Unauthorized { .. } => 401,
+        ApiError::Status { status, .. } => status.as_u16(),
+        other => panic!("expected an HTTP error, got {:?}", other),
+    }
+}
+
+#[test]
+fn steps_are_set_added_and_summarized() {
+    let server = start();
+    let api = register(&server, "walker");
+
+    let summary = api.steps_summary().unwrap();
+    assert_eq!((summary.daily, summary.monthly, summary.yearly), (0, 0, 0));
+
+    let set = api.set_steps(&StepsRequest { steps: 4000, date: None }).unwrap();
+    assert_eq!(set.steps, 4000);
+    assert_eq!(set.date, Some(Local::now().date_naive().to_string()));
+    assert_eq!(api.add_steps(&StepsRequest { steps: 1500, date: None }).unwrap().steps, 5500);
+    assert_eq!(api.set_steps(&StepsRequest { steps: 5000, date: None }).unwrap().steps, 5000);
+
+    let summary = api.steps_summary().unwrap();
+    assert_eq!((summary.daily, summary.monthly, summary.yearly), (5000, 5000, 5000));
+}
+
+#[test]
+fn water_and_sleep_are_recorded() {
+    let server = start();
+    let api = register(&server, "drinker");
+
+    api.set_water(&WaterRequest { ml: 500, date: None }).unwrap();
+    assert_eq!(api.add_water(&WaterRequest { ml: 1000, date: None }).unwrap().ml, 1500);
+    let water = api.water_summary().unwrap();
+    assert_eq!(water.daily_ml, 1500);
+    assert_eq!(water.daily_display, "1.5 L");
+
+    let night = Some("2025-03-14".to_string());
+    api.set_sleep(&SleepRequest { minutes: 420, date: night.clone() }).unwrap();
+    api.set_sleep(&SleepRequest { minutes: 450, date: night }).unwrap();
+    api.set_sleep(&SleepRequest { minutes: 400, date: Some("2025-03-01".to_string()) }).unwrap();
+    api.set_sleep(&SleepRequest { minutes: 300, date: Some("2025-01-20".to_string()) }).unwrap();
+    api.set_sleep(&SleepRequest { minutes: 480, date: Some("2024-12-31".to_string()) }).unwrap();
+
+    let sleep = api.sleep_summary("2025-03-14").unwrap();
+    assert_eq!((sleep.daily_minutes, sleep.monthly_minutes, sleep.yearly_minutes), (450, 850, 1150));
+}
+
+#[test]
+fn login_issues_working_tokens() {
+    let server = start();
+    register(&server, "walker");
+    let anonymous = client(&server, "");
+
+    let token = anonymous
+        .login(&LoginRequest {
+            username: "Walker".to_string(),
+            password: "correct horse".to_string(),
+        })
+        .unwrap()
+        .token;
+    assert_eq!(token.len(), 64);
+    assert!(client(&server, &token).steps_summary().is_ok());
+    assert!(client(&server, &token).with_auth_mode(AuthMode::Query).steps_summary().is_ok());
+
+    let wrong_password = anonymous.login(&LoginRequest {
+        username: "walker".to_string(),
+        password: "battery staple".to_string(),
+    });
+    assert_eq!(status(wrong_password.unwrap_err()), 401);
+    let unknown_user = anonymous.login(&LoginRequest {
+        username: "runner".to_string(),
+        password: "correct horse".to_string(),
+    });
+    assert_eq!(status(unknown_user.unwrap_err()), 401);
+}
+
+#[test]
+fn unknown_users_take_as_long_as_wrong_passwords() {
+    let server = start();
+    register(&server, "walker");
+    let anonymous = client(&server, "");
+    let timed_login = |username: &str| {
+        let started = Instant::now();
+        let result = anonymous.login(&LoginRequest {
+            username: username.to_string(),
+            password: "battery staple".to_string(),
+        });
+        assert_eq!(status(result.unwrap_err()), 401);
+        started.elapsed()
+    };
+
+    // Both pay for a password hash check, so neither answers much sooner
+    timed_login("nobody");
+    let wrong_password = timed_login("walker");
+    let unknown_user = timed_login("runner");
+    assert!(unknown_user * 4 >= wrong_password, "{:?} vs {:?}", unknown_user, wrong_password);
+}
+
+#[test]
+fn a_fixed_pool_serves_many_requests_at_once() {
+    let server = start();
+    let token = client(&server, "")
+        .register(&RegisterRequest {
+            username: "walker".to_string(),
+            password: "correct horse".to_string(),
+        })
+        .unwrap()
+        .token;
+
+    let url = server.url();
+    let requests: Vec<_> = (0..32)
+        .map(|_| {
+            let api = ApiClient::new(url.clone(), token.clone()).unwrap();
+            thread::spawn(move || api.steps_summary().is_ok())
+        })
+        .collect();
+    assert!(requests.into_iter().all(|request| request.join().unwrap()));
+
+    // Every worker stops when the server is dropped
+    drop(server);
+}
+
+#[test]
+fn requests_need_a_valid_token() {
+    let server = start();
+    register(&server, "walker");
+
+    assert_eq!(status(client(&server, "").steps_summary().unwrap_err()), 401);
+    match client(&server, "not-a-token").water_summary() {
+        Err(ApiError::Unauthorized { message }) => assert_eq!(message, "Invalid token"),
+        other => panic!("expected 401, got {:?}", other),
+    }
+}
+
+#[test]
+fn accounts_are_separate() {
+    let server = start();
+    let walker = register(&server, "walker");
+    let runner = register(&server, "runner");
+
+    walker.set_steps(&StepsRequest { steps: 1234, date: None }).unwrap();
+    assert_eq!(runner.steps_summary().unwrap().daily, 0);
+
+    let taken = client(&server, "").register(&RegisterRequest {
+        username: "RUNNER".to_string(),
+        password: "another password".to_string(),
+    });
+    assert_eq!(status(taken.unwrap_err()), 409);
+}
+
+#[test]
+fn invalid_input_is_rejected() {
+    let server = start();
+    let anonymous = client(&server, "");
+    let short_password = anonymous.register(&RegisterRequest {
+        username: "walker".to_string(),
+        password: "short".to_string(),
+    });
+    assert_eq!(status(short_password.unwrap_err()), 400);
+    let bad_username = anonymous.register(&RegisterRequest {
+        username: "no spaces".to_string(),
+        password: "correct horse".to_string(),
+    });
+    assert_eq!(status(bad_username.unwrap_err()), 400);
+
+    let api = register(&server, "walker");
+    let negative = api.add_steps(&StepsRequest { steps: -5, date: None });
+    assert_eq!(status(negative.unwrap_err()), 400);
+    let bad_date = api.set_steps(&StepsRequest { steps: 5, date: Some("31/01/2025".to_string()) });
+    assert_eq!(status(bad_date.unwrap_err()), 400);
+    assert_eq!(status(api.sleep_summary("yesterday").unwrap_err()), 400);
+    let oversleep = api.set_sleep(&SleepRequest { minutes: 1441, date: None });
+    assert_eq!(status(oversleep.unwrap_err()), 400);
+}
+
+#[test]
+fn data_survives_a_restart() {
+    let dir = TempDir::new("step-server");
+    let database = dir.path().join("server.db");
+
+    {
+        let server = Server::start("127.0.0.1:0", Store::open(&database).unwrap()).unwrap();
+        let api = register(&server, "walker");
+        api.set_steps(&StepsRequest { steps: 777, date: None }).unwrap();
+    }
+
+    let server = Server::start("127.0.0.1:0", Store::open(&database).unwrap()).unwrap();
+    let token = client(&server, "")
+        .login(&LoginRequest {
+            username: "walker".to_string(),
+            password: "correct horse".to_string(),
+        })
+        .unwrap()
+        .token;
+    assert_eq!(client(&server, &token).steps_summary().unwrap().daily, 777);
+}
+
+#[test]
+fn daily_history_lists_each_day_in_range() {
+    let server = start();
+    let api = register(&server, "streaker");
+
+    for (day, steps) in [("2025-03-01", 12_000), ("2025-03-02", 9_000), ("2025-03-04", 10_000), ("2025-04-01", 1)] {
+        api.set_steps(&StepsRequest { steps, date: Some(day.to_string()) }).unwrap();
+    }
+    api.set_water(&WaterRequest { ml: 2000, date: Some("2025-03-01".to_string()) }).unwrap();
+
+    let history = api.daily_history("steps", "2025-03-01", "2025-03-31").unwrap();
+    let days: Vec<(String, i64)> = history.days.into_iter().map(|day| (day.date, day.value)).collect();
+    assert_eq!(
+        days,
+        vec![
+            ("2025-03-01".to_string(), 12_000),
+            ("2025-03-02".to_string(), 9_000),
+            ("2025-03-04".to_string(), 10_000)
+        ]
+    );
+    assert_eq!(api.daily_history("water", "2025-03-01", "2025-03-01").unwrap().days.len(), 1);
+
+    assert_eq!(status(api.daily_history("steps", "2025-03-02", "2025-03-01").unwrap_err()), 400);
+    assert_eq!(status(api.daily_history("steps", "2023-01-01", "2025-03-01").unwrap_err()), 400);
+    assert_eq!(status(client(&server, "").daily_history("steps", "2025-03-01", "2025-03-02").unwrap_err()), 401);
+}
+
+#[test]
+fn store_buckets_totals_by_day_month_and_year() {
+    let store = Store::open_in_memory().unwrap();
+    let user = store.create_user("walker", "hash").unwrap();
+    assert!(matches!(store.create_user("WALKER", "hash"), Err(StoreError::UsernameTaken)));
+
+    store.set(Series::Steps, user, date("2025-02-28"), 1000).unwrap();
+    store.set(Series::Steps, user, date("2025-03-01"), 200).unwrap();
+    assert_eq!(store.add(Series::Steps, user, date("2025-03-01"), 30).unwrap(), 230);
+    store.set(Series::Steps, user, date("2024-03-01"), 5).unwrap();
+    store.set(Series::Water, user, date("2025-03-01"), 9999).unwrap();
+
+    assert_eq!(
+        store.totals(Series::Steps, user, date("2025-03-01")).unwrap(),
+        Totals { daily: 230, monthly: 230, yearly: 1230 }
+    );
+    assert_eq!(
+        store.totals(Series::Steps, user, date("2025-03-02")).unwrap(),
+        Totals { daily: 0, monthly: 230, yearly: 1230 }
+    );
+}
+
+#[test]
+fn volumes_are_formatted_like_the_api() {
+    assert_eq!(format_volume(0), "0 ml");
+    assert_eq!(format_volume(750), "750 ml");
+    assert_eq!(format_volume(1000), "1 L");
+    assert_eq!(format_volume(1500), "1.5 L");
+    assert_eq!(format_volume(2250), "2.3 L");
+    assert_eq!(format_volume(9999), "10 L");
+    assert_eq!(format_volume(300_000), "300 L");
+}
@@ -0,0 +1,142 @@
+mod support;
+
+use chrono::{Duration, Local, NaiveDate};
+use discord_rpc::metrics::Metric;
+use discord_rpc::mock::{MockApi, MockResponse};
+use discord_rpc::presence::DryRunPresence;
+use discord_rpc::retry::RetryPolicy;
+use discord_rpc::rotation::Rotation;
+use discord_rpc::{ApiClient, History, Streak, Totals};
+use serde_json::json;
+use support::{date, steps_metric, steps_options, FixedMetric};
+
+fn days(values: &[(&str, i64)]) -> Vec<(NaiveDate, i64)> {
+    values.iter().map(|(day, value)| (date(day), *value)).collect()
+}
+
+fn client(mock: &MockApi) -> ApiClient {
+    ApiClient::new(mock.url(), "").unwrap().with_retry_policy(RetryPolicy::none())
+}
+
+#[test]
+fn current_and_longest_streaks() {
+    let values = days(&[
+        ("2025-03-01", 10_000),
+        ("2025-03-02", 12_000),
+        ("2025-03-03", 11_000),
+        ("2025-03-04", 4_000),
+        ("2025-03-05", 10_500),
+        ("2025-03-06", 10_000),
+    ]);
+    let streak = Streak::from_days(values, 10_000, date("2025-03-06"));
+    assert_eq!(streak, Streak { current: 2, longest: 3 });
+}
+
+#[test]
+fn today_only_breaks_the_streak_once_it_is_over() {
+    let values = days(&[("2025-03-04", 10_000), ("2025-03-05", 10_000), ("2025-03-06", 2_000)]);
+    let streak = Streak::from_days(values.clone(), 10_000, date("2025-03-06"));
+    assert_eq!(streak, Streak { current: 2, longest: 2 });
+
+    // The next day, the missed goal has ended it
+    let streak = Streak::from_days(values, 10_000, date("2025-03-07"));
+    assert_eq!(streak, Streak { current: 0, longest: 2 });
+}
+
+#[test]
+fn missing_days_break_streaks() {
+    let values = days(&[("2025-03-01", 10_000), ("2025-03-03", 10_000), ("2025-03-04", 10_000)]);
+    let streak = Streak::from_days(values, 10_000, date("2025-03-04"));
+    assert_eq!(streak, Streak { current: 2, longest: 2 });
+    assert_eq!(streak.to_string(), "🔥 2-day streak");
+
+    assert_eq!(Streak::from_days(Vec::new(), 10_000, date("2025-03-04")), Streak::default());
+}
+
+#[test]
+fn streaks_are_shown_in_state_and_obs() {
+    let mut metric = steps_metric(8421, steps_options());
+
+    metric.set_streak(Some(Streak { current: 12, longest: 20 }));
+    assert_eq!(metric.state(), "Monthly: 200.00K | Yearly: 1.50M | 🔥 12-day streak");
+    assert!(metric.obs_text().ends_with("\nStreak: 12 days (best: 20)"));
+
+    // A broken streak is left out of the presence but kept in OBS
+    metric.set_streak(Some(Streak { current: 0, longest: 20 }));
+    assert_eq!(metric.state(), "Monthly: 200.00K | Yearly: 1.50M");
+    assert!(metric.obs_text().ends_with("\nStreak: 0 days (best: 20)"));
+}
+
+#[test]
+fn rotation_counts_streaks_from_the_api_history() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let today = Local::now().date_naive();
+    let history: Vec<_> = (1..=3)
+        .map(|ago| json!({ "date": (today - Duration::days(ago)).to_string(), "value": 10_000 }))
+        .collect();
+    mock.set_response("/api/steps/history", MockResponse::ok(json!({ "days": history })));
+
+    let metric = FixedMetric::new("steps", 1, "", "")
+        .with_totals(10_200, 0, 0)
+        .with_goal(10_000);
+    let mut rotation = Rotation::new(vec![metric.boxed()]);
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+
+    let api = client(&mock);
+    rotation.step(&api, &mut presence).unwrap();
+    rotation.step(&api, &mut presence).unwrap();
+
+    assert_eq!(rotation.metrics()[0].streak(), Some(Streak { current: 4, longest: 4 }));
+    // The days before today are only asked for once a day
+    assert_eq!(mock.request_count("/api/steps/history"), 1);
+}
+
+#[test]
+fn rotation_falls_back_to_the_local_history() {
+    // The mock has no history endpoint configured and answers 404
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let history = History::open_in_memory().unwrap();
+    let yesterday = Local::now() - Duration::days(1);
+    let totals = Totals {
+        daily: 10_000,
+        monthly: 10_000,
+        yearly: 10_000,
+    };
+    history.append("steps", yesterday, totals).unwrap();
+
+    let metric = FixedMetric::new("steps", 1, "", "").with_totals(500, 0, 0).with_goal(10_000);
+    let mut rotation = Rotation::new(vec![metric.boxed()]).with_history(history);
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    rotation.step(&client(&mock), &mut presence).unwrap();
+
+    assert_eq!(rotation.metrics()[0].streak(), Some(Streak { current: 1, longest: 1 }));
+}
+
+#[test]
+fn a_failed_history_is_only_asked_for_once_a_day() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    mock.set_response("/api/steps/history", MockResponse::error(500, "no history here"));
+
+    let metric = FixedMetric::new("steps", 1, "", "").with_totals(500, 0, 0).with_goal(10_000);
+    let mut rotation = Rotation::new(vec![metric.boxed()]);
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    let api = client(&mock);
+    for _ in 0..3 {
+        rotation.step(&api, &mut presence).unwrap();
+    }
+
+    assert_eq!(mock.request_count("/api/steps/history"), 1);
+    // Without the days before today there is no streak to show
+    assert_eq!(rotation.metrics()[0].streak(), None);
+}
+
+#[test]
+fn metrics_without_goals_have_no_streak() {
+    let mock = MockApi::start("127.0.0.1:0").unwrap();
+    let mut rotation = Rotation::new(vec![FixedMetric::new("steps", 1, "", "").with_totals(1, 1, 1).boxed()]);
+    let mut presence = DryRunPresence::new(Vec::new(), rotation.metrics());
+    rotation.step(&client(&mock), &mut presence).unwrap();
+
+    assert_eq!(rotation.metrics()[0].streak(), None);
+    assert_eq!(mock.request_count("/api/steps/history"), 0);
+}
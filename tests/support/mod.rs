@@ -0,0 +1,424 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use discord_rpc::metrics::{Metric, MetricOptions, StepsMetric};
+use discord_rpc::template::Unit;
+use discord_rpc::{ApiClient, ApiError, Config, ConfigError, Streak, Totals};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh directory under the system temp dir, removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        let n = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("{}-{}-{}", prefix, std::process::id(), n));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Something the fake Discord client received.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Handshake { client_id: u64 },
+    /// `activity` is `None` when the activity was cleared.
+    SetActivity { client_id: u64, pid: u64, activity: Option<Value> },
+}
+
+#[derive(Default)]
+struct Shared {
+    records: Mutex<Vec<Record>>,
+    // Open connections by the client ID they shook hands with
+    connections: Mutex<HashMap<u64, Vec<UnixStream>>>,
+    rejected: Mutex<HashSet<u64>>,
+    stopped: AtomicBool,
+}
+
+/// Impersonates the Discord desktop client on a Unix socket: answers the
+/// handshake and `SET_ACTIVITY`, records everything it is sent, and can
+/// drop connections on demand.
+pub struct FakeDiscord {
+    dir: TempDir,
+    path: PathBuf,
+    shared: Arc<Shared>,
+}
+
+impl FakeDiscord {
+    pub fn start() -> Self {
+        let dir = TempDir::new("fake-discord");
+        let path = dir.path().join("discord-ipc-0");
+        let listener = UnixListener::bind(&path).unwrap();
+        let shared = Arc::new(Shared::default());
+
+        let accepting = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accepting.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let shared = Arc::clone(&accepting);
+                thread::spawn(move || {
+                    let _ = serve(stream, &shared);
+                });
+            }
+        });
+
+        Self { dir, path, shared }
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.shared.records.lock().unwrap().clone()
+    }
+
+    pub fn clear_records(&self) {
+        self.shared.records.lock().unwrap().clear();
+    }
+
+    /// Client IDs of every handshake, in order.
+    pub fn handshakes(&self) -> Vec<u64> {
+        self.records()
+            .into_iter()
+            .filter_map(|record| match record {
+                Record::Handshake { client_id } => Some(client_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every activity set (not cleared) by `client_id`, in order.
+    pub fn activities(&self, client_id: u64) -> Vec<Value> {
+        self.records()
+            .into_iter()
+            .filter_map(|record| match record {
+                Record::SetActivity { client_id: id, activity: Some(activity), .. } if id == client_id => Some(activity),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Answer future handshakes from `client_id` by closing the connection,
+    /// as Discord does for an unknown application.
+    pub fn reject(&self, client_id: u64) {
+        self.shared.rejected.lock().unwrap().insert(client_id);
+    }
+
+    /// Drop every connection made by `client_id`, as if Discord restarted.
+    pub fn disconnect(&self, client_id: u64) {
+        if let Some(streams) = self.shared.connections.lock().unwrap().remove(&client_id) {
+            for stream in streams {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    /// Drop every open connection.
+    pub fn disconnect_all(&self) {
+        for (_, streams) in self.shared.connections.lock().unwrap().drain() {
+            for stream in streams {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+impl Drop for FakeDiscord {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.disconnect_all();
+        // Wake the accept loop so it sees the stop flag
+        let _ = UnixStream::connect(&self.path);
+    }
+}
+
+fn serve(mut stream: UnixStream, shared: &Shared) -> io::Result<()> {
+    let (opcode, handshake) = read_frame(&mut stream)?;
+    assert_eq!(opcode, 0, "first frame must be a handshake");
+    let client_id: u64 = handshake["client_id"].as_str().unwrap().parse().unwrap();
+    shared.records.lock().unwrap().push(Record::Handshake { client_id });
+
+    if shared.rejected.lock().unwrap().contains(&client_id) {
+        return write_frame(&mut stream, 2, &json!({ "code": 4000, "message": "Invalid Client ID" }));
+    }
+    shared
+        .connections
+        .lock()
+        .unwrap()
+        .entry(client_id)
+        .or_default()
+        .push(stream.try_clone()?);
+
+    let ready = json!({
+        "cmd": "DISPATCH",
+        "evt": "READY",
+        "data": { "v": 1, "user": { "id": "1", "username": "tester" } },
+        "nonce": null,
+    });
+    write_frame(&mut stream, 1, &ready)?;
+
+    loop {
+        let (opcode, payload) = read_frame(&mut stream)?;
+        match opcode {
+            1 => {
+                assert_eq!(payload["cmd"], "SET_ACTIVITY");
+                let args = &payload["args"];
+                let activity = Some(args["activity"].clone()).filter(|activity| !activity.is_null());
+                shared.records.lock().unwrap().push(Record::SetActivity {
+                    client_id,
+                    pid: args["pid"].as_u64().unwrap(),
+                    activity: activity.clone(),
+                });
+                let response = json!({ "cmd": "SET_ACTIVITY", "evt": null, "data": activity, "nonce": payload["nonce"] });
+                write_frame(&mut stream, 1, &response)?;
+            }
+            3 => write_frame(&mut stream, 4, &payload)?,
+            _ => return Ok(()),
+        }
+    }
+}
+
+// Frame encoding written independently of discord_rpc::ipc, so the two
+// check each other
+fn read_frame(stream: &mut UnixStream) -> io::Result<(u32, Value)> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header)?;
+    let opcode = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut body = vec![0u8; len as usize];
+    stream.read_exact(&mut body)?;
+    Ok((opcode, serde_json::from_slice(&body)?))
+}
+
+fn write_frame(stream: &mut UnixStream, opcode: u32, payload: &Value) -> io::Result<()> {
+    let body = serde_json::to_vec(payload)?;
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&opcode.to_le_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&body);
+    stream.write_all(&frame)
+}
+
+/// A metric with fixed values that never touches the API.
+pub struct FixedMetric {
+    id: &'static str,
+    options: MetricOptions,
+    details: String,
+    state: String,
+    totals: Option<Totals>,
+    fetched_at: Option<DateTime<Local>>,
+    streak: Option<Streak>,
+}
+
+impl FixedMetric {
+    pub fn new(id: &'static str, client_id: u64, details: &str, state: &str) -> Self {
+        Self {
+            id,
+            options: MetricOptions {
+                client_id,
+                image_key: format!("{}_image", id),
+                hover_text: format!("{} hover", id),
+                ..Default::default()
+            },
+            details: details.to_string(),
+            state: state.to_string(),
+            totals: None,
+            fetched_at: None,
+            streak: None,
+        }
+    }
+
+    pub fn with_totals(mut self, daily: i64, monthly: i64, yearly: i64) -> Self {
+        self.totals = Some(Totals { daily, monthly, yearly });
+        self
+    }
+
+    pub fn with_goal(mut self, goal: i64) -> Self {
+        self.options.goal = Some(goal);
+        self
+    }
+
+    pub fn boxed(self) -> Box<dyn Metric> {
+        Box::new(self)
+    }
+}
+
+impl Metric for FixedMetric {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn label(&self) -> &str {
+        self.id
+    }
+
+    fn options(&self) -> &MetricOptions {
+        &self.options
+    }
+
+    fn fetch(&mut self, _api: &ApiClient) -> Result<(), ApiError> {
+        self.fetched_at = Some(Local::now());
+        Ok(())
+    }
+
+    fn built_in_details(&self) -> String {
+        self.details.clone()
+    }
+
+    fn built_in_state(&self) -> String {
+        self.state.clone()
+    }
+
+    fn built_in_obs_text(&self) -> String {
+        self.details.clone()
+    }
+
+    fn totals(&self) -> Option<Totals> {
+        self.totals
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Count(self.id)
+    }
+
+    fn format_value(&self, value: i64) -> String {
+        value.to_string()
+    }
+
+    fn snapshot(&self) -> Option<Value> {
+        None
+    }
+
+    fn fetched_at(&self) -> Option<DateTime<Local>> {
+        self.fetched_at
+    }
+
+    fn streak(&self) -> Option<Streak> {
+        self.streak
+    }
+
+    fn set_streak(&mut self, streak: Option<Streak>) {
+        self.streak = streak;
+    }
+
+    fn restore(&mut self, _snapshot: Value, fetched_at: DateTime<Local>) -> Result<(), serde_json::Error> {
+        self.fetched_at = Some(fetched_at);
+        Ok(())
+    }
+}
+
+/// A client for an address nothing listens on, for code paths that never
+/// reach the API.
+pub fn offline_api() -> ApiClient {
+    ApiClient::new("http://127.0.0.1:9", "").unwrap()
+}
+
+/// A config file holding `body` after an API URL and a single Discord client,
+/// written to `dir`.
+pub fn config_file(dir: &TempDir, body: &str) -> PathBuf {
+    let path = dir.path().join("config.toml");
+    let header = r#"
+        [api]
+        url = "http://127.0.0.1:9"
+
+        [discord]
+        mode = "single"
+        client_id = 1
+    "#;
+    fs::write(&path, format!("{}\n{}", header, body)).unwrap();
+    path
+}
+
+/// The config read from a file holding `body`, see [`config_file`].
+pub fn write_config(body: &str) -> Config {
+    let dir = TempDir::new("config");
+    Config::from_file(&config_file(&dir, body)).unwrap()
+}
+
+/// Every problem `validate` finds, none if the config is valid.
+pub fn config_problems(config: &Config) -> Vec<String> {
+    match config.validate() {
+        Ok(()) => Vec::new(),
+        Err(ConfigError::Invalid(problems)) => problems,
+        Err(e) => panic!("expected validation problems, got {:?}", e),
+    }
+}
+
+/// Steps shown with the `walking` image and "I'm walking here!".
+pub fn steps_options() -> MetricOptions {
+    MetricOptions {
+        client_id: 1,
+        image_key: "walking".to_string(),
+        hover_text: "I'm walking here!".to_string(),
+        ..Default::default()
+    }
+}
+
+/// A steps metric that fetched `daily` steps just now, see [`steps_summary`].
+pub fn steps_metric(daily: i64, options: MetricOptions) -> StepsMetric {
+    let mut metric = StepsMetric::new(options);
+    metric.restore(steps_summary(daily), Local::now()).unwrap();
+    metric
+}
+
+/// `daily` steps today, 200K this month and 1.5M this year.
+pub fn steps_summary(daily: i64) -> Value {
+    json!({ "daily": daily, "monthly": 200_000, "yearly": 1_500_000 })
+}
+
+/// 1.5 L of water today, 30 L this month and 300 L this year.
+pub fn water_summary() -> Value {
+    json!({
+        "daily_ml": 1500,
+        "monthly_ml": 30_000,
+        "yearly_ml": 300_000,
+        "daily_display": "1.5 L",
+        "monthly_display": "30 L",
+        "yearly_display": "300 L",
+    })
+}
+
+/// 7h 30m of sleep today, 150h this month and 1500h this year.
+pub fn sleep_summary() -> Value {
+    json!({ "daily_minutes": 450, "monthly_minutes": 9000, "yearly_minutes": 90_000 })
+}
+
+/// A day given as `YYYY-MM-DD`.
+pub fn date(date: &str) -> NaiveDate {
+    date.parse().unwrap()
+}
+
+/// The start of `hour` on a day given as `YYYY-MM-DD`, local time.
+pub fn at(day: &str, hour: u32) -> DateTime<Local> {
+    Local
+        .from_local_datetime(&date(day).and_hms_opt(hour, 0, 0).unwrap())
+        .unwrap()
+}
@@ -0,0 +1,141 @@
+mod support;
+
+use chrono::{Local, TimeZone};
+use discord_rpc::metrics::{Metric, MetricOptions, SleepMetric, StepsMetric, WaterMetric};
+use discord_rpc::presence::Activity;
+use discord_rpc::template::{Template, TemplateError, Templates, Unit, Value, Variables};
+use discord_rpc::Streak;
+use support::{config_problems, sleep_summary, steps_metric, steps_options, water_summary, write_config};
+
+fn options(goal: Option<i64>, templates: Templates) -> MetricOptions {
+    MetricOptions {
+        goal,
+        templates,
+        ..steps_options()
+    }
+}
+
+fn template(source: &str) -> Option<Template> {
+    Some(Template::parse(source).unwrap())
+}
+
+#[test]
+fn variables_render_with_filters() {
+    let mut variables = Variables::new();
+    variables.set("daily", Value::Amount(8421, Unit::Count("steps")));
+    variables.set("yearly", Value::Amount(1_234_567, Unit::Count("steps")));
+    variables.set("monthly", Value::Amount(300, Unit::Count("push-ups")));
+    variables.set("goal", Value::Amount(10_000, Unit::Count("steps")));
+    variables.set("goal_pct", Value::Number(84));
+    variables.set("label", Value::Text("Steps".to_string()));
+
+    let render = |source: &str| Template::parse(source).unwrap().render(&variables);
+    assert_eq!(render("{daily:short} steps — {goal_pct}%"), "8.42K steps — 84%");
+    assert_eq!(render("{daily}"), "8.42K");
+    assert_eq!(render("{daily:raw} / {yearly:comma}"), "8421 / 1,234,567");
+    assert_eq!(render("{daily:unit}"), "8421 steps");
+    // Counts are named by the metric they belong to
+    assert_eq!(render("{monthly:unit}"), "300 push-ups");
+    assert_eq!(render("{label:upper} {{{ label }}}"), "STEPS {Steps}");
+    // Text filters keep the default number format
+    assert_eq!(render("{daily:upper} / {goal_pct:lower}"), "8.42K / 84");
+    assert_eq!(render("{goal:short} / {goal:compact}"), "10K / 10.00K");
+    // Variables without a value render as nothing
+    assert_eq!(render("Streak: {streak}"), "Streak: ");
+}
+
+#[test]
+fn invalid_templates_are_rejected() {
+    assert_eq!(Template::parse("{dialy}"), Err(TemplateError::UnknownVariable("dialy".to_string())));
+    assert_eq!(Template::parse("{daily:tiny}"), Err(TemplateError::UnknownFilter("tiny".to_string())));
+    assert_eq!(Template::parse("{daily"), Err(TemplateError::Unbalanced));
+    assert_eq!(Template::parse("daily}"), Err(TemplateError::Unbalanced));
+}
+
+#[test]
+fn templates_replace_the_built_in_texts() {
+    let templates = Templates {
+        details: template("{daily:short} steps — {goal_pct}%"),
+        state: template("{remaining:short} to go | 🔥 {streak} days"),
+        hover_text: template("{yearly:comma} steps this year"),
+        obs: template("{label}: {daily:raw}/{goal:raw}"),
+    };
+    let mut metric = steps_metric(8421, options(Some(10_000), templates));
+    metric.set_streak(Some(Streak { current: 12, longest: 20 }));
+
+    assert_eq!(metric.details(), "8.42K steps — 84%");
+    assert_eq!(metric.state(), "1.58K to go | 🔥 12 days");
+    assert_eq!(metric.obs_text(), "Steps: 8421/10000");
+    let activity = Activity::for_metric(&metric);
+    assert_eq!(activity.assets.unwrap().large_text, "1,500,000 steps this year");
+}
+
+#[test]
+fn amounts_use_the_metric_unit() {
+    let templates = Templates {
+        details: template("{daily} of {goal} ({daily:raw} ml)"),
+        ..Templates::default()
+    };
+    let mut water = WaterMetric::new(options(Some(2500), templates));
+    water.restore(water_summary(), Local::now()).unwrap();
+    assert_eq!(water.details(), "1.5 L of 2.5 L (1500 ml)");
+
+    let fetched_at = Local.with_ymd_and_hms(2025, 3, 4, 7, 5, 0).unwrap();
+    let templates = Templates {
+        state: template("{daily} slept, as of {as_of}"),
+        ..Templates::default()
+    };
+    let mut sleep = SleepMetric::new(options(None, templates));
+    sleep.restore(sleep_summary(), fetched_at).unwrap();
+    assert_eq!(sleep.state(), "7h 30m slept, as of 07:05");
+}
+
+#[test]
+fn empty_texts_are_left_out_of_the_activity() {
+    let templates = Templates {
+        state: template("{streak}"),
+        ..Templates::default()
+    };
+    let metric = steps_metric(8421, options(None, templates));
+
+    // Discord refuses an activity with an empty state
+    assert_eq!(metric.state(), "");
+    let activity = serde_json::to_value(Activity::for_metric(&metric)).unwrap();
+    assert_eq!(activity["details"], "Today: 8.42K");
+    assert!(activity.get("state").is_none());
+}
+
+#[test]
+fn templates_wait_for_data() {
+    let templates = Templates {
+        details: template("{daily} steps"),
+        ..Templates::default()
+    };
+    let metric = StepsMetric::new(options(None, templates));
+    assert_eq!(metric.details(), "No data yet");
+}
+
+#[test]
+fn templates_are_read_and_validated_from_the_config_file() {
+    let config = write_config(
+        r#"
+        [steps.templates]
+        details = "{daily:short} steps"
+        obs = "{daily:raw}"
+
+        [water.templates]
+        state = "{monthy} this month"
+        "#,
+    );
+    assert_eq!(
+        config_problems(&config),
+        vec!["[water.templates] state: unknown variable 'monthy'".to_string()]
+    );
+
+    let metrics = config.enabled_metrics();
+    let templates = &metrics[0].options().templates;
+    assert_eq!(templates.details.as_ref().unwrap().source(), "{daily:short} steps");
+    assert_eq!(templates.obs.as_ref().unwrap().source(), "{daily:raw}");
+    assert!(templates.state.is_none());
+    assert!(metrics[1].options().templates.state.is_none());
+}